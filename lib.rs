@@ -2,7 +2,115 @@
 
 #[ink::contract]
 mod erc20 {
-    use ink::storage::Mapping;
+    use ink::env::call::{build_call, ExecutionInput, Selector};
+    use ink::env::hash::Blake2x256;
+    use ink::env::{CallFlags, DefaultEnvironment};
+    use ink::prelude::string::String;
+    use ink::prelude::vec::Vec;
+    use ink::storage::{Lazy, Mapping};
+
+    /// Decimals used by constructors that do not take metadata.
+    const DEFAULT_DECIMALS: u8 = 18;
+
+    /// Basis points denominator used for percentage based settings.
+    const MAX_BPS: u16 = 10_000;
+
+    /// Default bound on `known_accounts`, keeping its storage cell well below
+    /// the size the runtime can load.
+    const DEFAULT_MAX_KNOWN_ACCOUNTS: u32 = 256;
+
+    /// Hard bound on `known_accounts`. The whole list lives in one storage
+    /// cell, which must fit the 16 KiB static buffer at 32 bytes per account.
+    const MAX_KNOWN_ACCOUNTS: u32 = 500;
+
+    /// Default number of blocks a prepared transfer stays executable.
+    const DEFAULT_TICKET_TTL: BlockNumber = 10;
+
+    /// Highest reason code accepted by `burn_with_reason`.
+    const MAX_BURN_REASON: u8 = 3;
+
+    /// Number of entries kept in `supply_history`.
+    const MAX_SUPPLY_HISTORY: usize = 64;
+
+    /// Version of the contract code, bumped with every release.
+    const LOGIC_VERSION: u32 = 2;
+
+    /// Storage layout this code writes. After a `set_code_hash` upgrade, new
+    /// code compares it with its own to decide whether storage needs a
+    /// migration; no layout change has needed one yet.
+    const STORAGE_VERSION: u32 = 1;
+
+    /// Number of epochs kept per pair in `allowance_history`.
+    const MAX_ALLOWANCE_HISTORY: usize = 8;
+
+    /// Largest number of entries a batch message accepts, to keep calls
+    /// within block limits.
+    const MAX_BATCH: usize = 64;
+
+    /// Version of the permit signing scheme, part of `domain_separator`.
+    const PERMIT_VERSION: &[u8] = b"1";
+
+    /// Returns `bps` basis points of `amount`, rounded down, without
+    /// overflowing on large amounts.
+    fn bps_of(amount: Balance, bps: u16) -> Balance {
+        let denominator = Balance::from(MAX_BPS);
+        let bps = Balance::from(bps);
+        amount / denominator * bps + amount % denominator * bps / denominator
+    }
+
+    /// The all-zero account, used as the recipient of burns.
+    fn zero_account() -> AccountId {
+        AccountId::from([0u8; 32])
+    }
+
+    /// Integer square root, rounded down.
+    fn isqrt(n: Balance) -> Balance {
+        if n < 2 {
+            return n;
+        }
+        // Newton's method starting from an upper bound on the root, chosen so
+        // that `x + n / x` cannot overflow.
+        let mut x = n / 2 + 1;
+        let mut y = (x + n / x) / 2;
+        while y < x {
+            x = y;
+            y = (x + n / x) / 2;
+        }
+        x
+    }
+
+    /// Returns `a * b / c` rounded down, using a 256 bit intermediate product
+    /// so share conversions cannot overflow. Saturates if the result does not
+    /// fit in a `u128`.
+    fn mul_div(a: u128, b: u128, c: u128) -> u128 {
+        if let Some(product) = a.checked_mul(b) {
+            return product / c;
+        }
+        // Compute the 256 bit product as (hi, lo) from 64 bit limbs.
+        const MASK: u128 = u64::MAX as u128;
+        let (a1, a0) = (a >> 64, a & MASK);
+        let (b1, b0) = (b >> 64, b & MASK);
+        let (p00, p01, p10, p11) = (a0 * b0, a0 * b1, a1 * b0, a1 * b1);
+        let mid = (p00 >> 64) + (p01 & MASK) + (p10 & MASK);
+        let lo = (p00 & MASK) | (mid << 64);
+        let hi = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+        if hi >= c {
+            return u128::MAX;
+        }
+        // Long division of (hi, lo) by c; the quotient fits since hi < c.
+        let mut rem = hi;
+        let mut quotient = 0;
+        for i in (0..128).rev() {
+            let carry = rem >> 127;
+            rem = (rem << 1) | ((lo >> i) & 1);
+            quotient <<= 1;
+            if carry == 1 || rem >= c {
+                rem = rem.wrapping_sub(c);
+                quotient |= 1;
+            }
+        }
+        quotient
+    }
 
     /// Defines the storage of your contract.
     /// Add new fields to the below struct in order
@@ -11,17 +119,361 @@ mod erc20 {
     #[derive(Default)]
     pub struct Erc20 {
         total_supply: Balance,
+        /// Holdings in shares. A holder's balance is its share of
+        /// `total_supply`, so a rebase only has to change `total_supply`.
         balances: Mapping<AccountId, Balance>,
+        total_shares: Balance,
         allowances: Mapping<(AccountId, AccountId), Balance>,
+        owner: Option<AccountId>,
+        supply_frozen: bool,
+        transfer_seq: u64,
+        /// pool id -> (owner, remaining budget)
+        pools: Mapping<u64, (AccountId, Balance)>,
+        pool_members: Mapping<(u64, AccountId), bool>,
+        next_pool_id: u64,
+        fee_bps: u16,
+        treasury: Option<AccountId>,
+        round_up_fees: bool,
+        nonces: Mapping<AccountId, u64>,
+        allow_zero_transfers: bool,
+        spender_count: Mapping<AccountId, u32>,
+        max_spenders: Option<u32>,
+        delegated_paused: bool,
+        bridge_accounts: Mapping<AccountId, bool>,
+        /// (max outflow in bps of total supply, window length)
+        outflow_limit: Option<(u16, Timestamp)>,
+        outflow_window_start: Timestamp,
+        outflow_in_window: Balance,
+        validator: Option<AccountId>,
+        /// Every account that has ever received tokens, in order of first receipt.
+        known_accounts: Lazy<Vec<AccountId>>,
+        known: Mapping<AccountId, bool>,
+        max_known_accounts: u32,
+        /// ticket id -> (from, to, value, prepared at block)
+        tickets: Mapping<u64, (AccountId, AccountId, Balance, BlockNumber)>,
+        next_ticket: u64,
+        ticket_ttl: BlockNumber,
+        mint_claims: Mapping<AccountId, Balance>,
+        used_keys: Mapping<(AccountId, [u8; 32]), bool>,
+        quadratic_voting: bool,
+        total_accounts_ever: u32,
+        paused: bool,
+        breaker_threshold: Option<Balance>,
+        allowance_expiry: Mapping<(AccountId, AccountId), Timestamp>,
+        /// account -> (guardians, approvals needed to recover it)
+        guardians: Mapping<AccountId, (Vec<AccountId>, u8)>,
+        /// (lost account, new account) -> guardians that approved the move
+        recovery_approvals: Mapping<(AccountId, AccountId), Vec<AccountId>>,
+        oracle: Option<AccountId>,
+        min_price: u128,
+        name: Option<String>,
+        symbol: Option<String>,
+        /// Set once at construction. There is deliberately no setter, since
+        /// changing it would reinterpret every stored balance.
+        decimals: u8,
+        /// recipient -> (shares, unlock block) for `locked_transfer_by_block`
+        block_locks: Mapping<AccountId, Vec<(Balance, BlockNumber)>>,
+        initialized: bool,
+        /// Before this time only the owner and whitelisted accounts may send.
+        trading_enabled_at: Timestamp,
+        trading_whitelist: Mapping<AccountId, bool>,
+        rich_events: bool,
+        /// (timestamp, new total supply) after each mint, burn or rebase,
+        /// oldest first. Only the last `MAX_SUPPLY_HISTORY` entries are kept.
+        supply_history: Lazy<Vec<(Timestamp, Balance)>>,
+        /// How long queued admin actions wait before they can run. Zero
+        /// disables the timelock and allows direct calls.
+        min_delay: Timestamp,
+        /// action id -> (action, earliest execution time)
+        queued_actions: Mapping<u64, (AdminAction, Timestamp)>,
+        next_action_id: u64,
+        transfer_count_out: Mapping<AccountId, u32>,
+        transfer_count_in: Mapping<AccountId, u32>,
+        /// (owner, spender, owner's allowance epoch) -> (limit per period,
+        /// period, spent in window, window start). Takes the place of the
+        /// plain allowance when set. Keying by epoch lets
+        /// `revoke_all_allowances` drop these along with plain allowances.
+        period_allowances:
+            Mapping<(AccountId, AccountId, u64), (Balance, Timestamp, Balance, Timestamp)>,
+        /// When each allowance was last set through `approve` or its variants.
+        approved_at: Mapping<(AccountId, AccountId), Timestamp>,
+        /// While set, only the owner and treasury may send, and only to
+        /// `distribution_recipients`.
+        distribution_phase: bool,
+        distribution_recipients: Mapping<AccountId, bool>,
+        min_supply: Option<Balance>,
+        max_supply: Option<Balance>,
+        router: Option<AccountId>,
+        /// beneficiary -> (total shares, released shares, start, end) of a
+        /// linear vesting schedule held in the contract's custody
+        vesting: Mapping<AccountId, (Balance, Balance, Timestamp, Timestamp)>,
+        verify_spenders: bool,
+        /// (sender, recipient) pairs that have used `grant`
+        granted: Mapping<(AccountId, AccountId), bool>,
+        /// Part of each balance the owner has frozen in place.
+        frozen: Mapping<AccountId, Balance>,
+        /// Initial allocations made at construction or `initialize`.
+        genesis: Lazy<Vec<(AccountId, Balance)>>,
+        /// Bumped by `revoke_all_allowances`, invalidating every allowance the
+        /// owner granted in earlier epochs.
+        allowance_epoch: Mapping<AccountId, u64>,
+        /// Epoch each stored allowance was granted in, absent for epoch 0.
+        allowance_epochs: Mapping<(AccountId, AccountId), u64>,
+        /// (owner, spender) -> (epoch, last approved value), oldest first
+        allowance_history: Mapping<(AccountId, AccountId), Vec<(u64, Balance)>>,
+        /// System accounts whose transfers skip pauses and policy limits.
+        exempt: Mapping<AccountId, bool>,
+        /// Layout version of this storage, `0` for contracts deployed before it
+        /// was tracked.
+        storage_version: u32,
+        /// Part of each balance its holder has staked, which cannot be moved
+        /// until unstaked.
+        staked: Mapping<AccountId, Balance>,
+        /// Chain id committed to by `domain_separator`. ink! cannot read it
+        /// from the runtime, so the owner sets it.
+        chain_id: u32,
+        /// Operators that may move all of an owner's tokens without an
+        /// allowance, keyed by `(owner, operator)`.
+        operators: Mapping<(AccountId, AccountId), bool>,
+        /// Identifies the token in `Transfer` topics. Fixed at construction.
+        tag: [u8; 8],
+        /// When set, the first tokens sent to an account with no balance come
+        /// with `seed_amount` native tokens from the contract for storage rent.
+        seed_new_holders: bool,
+        seed_amount: Balance,
+        /// Base units per reference unit for `total_supply_in_units`. Fixed at
+        /// construction.
+        unit_scale: u128,
+        /// Jurisdiction code of each account, assigned by the owner.
+        jurisdictions: Mapping<AccountId, u16>,
+        /// `(from, to)` jurisdiction pairs transfers may not cross.
+        forbidden_jurisdictions: Mapping<(u16, u16), bool>,
+        /// When set, accounts without a jurisdiction can neither send nor
+        /// receive.
+        restrict_unset_jurisdictions: bool,
+        /// When set, transfers below `dust_threshold` succeed without moving
+        /// anything or emitting events.
+        drop_dust: bool,
+        dust_threshold: Balance,
+        /// Account that instantiated the contract, the only one allowed to
+        /// call `initialize`.
+        instantiator: Option<AccountId>,
     }
 
-    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    /// The SCALE encoding of each variant is its index, which is part of the
+    /// ABI. Append new variants at the end; never insert or reorder.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub enum Error {
         BalanceTooLow,
         AllowanceTooLow,
+        NotOwner,
+        SupplyFrozen,
+        Overflow,
+        NotPoolMember,
+        PoolExhausted,
+        InvalidFee,
+        InvalidSignature,
+        Expired,
+        ZeroTransfer,
+        TooManySpenders,
+        DelegatedPaused,
+        InvalidBps,
+        GlobalLimitExceeded,
+        ValidationFailed,
+        TooManyAccounts,
+        TicketNotFound,
+        TicketExpired,
+        InvalidReason,
+        NothingToClaim,
+        DuplicateRequest,
+        CallbackFailed,
+        Paused,
+        InvalidThreshold,
+        NotGuardian,
+        RecoveryThresholdNotMet,
+        PriceFloorBreached,
+        InvalidRebase,
+        AboveBreakerThreshold,
+        AlreadyInitialized,
+        TradingNotStarted,
+        TipFailed,
+        NotInitialized,
+        TimelockNotElapsed,
+        ActionNotFound,
+        PeriodLimitExceeded,
+        SupplyMismatch,
+        DistributionPhaseActive,
+        AboveMaxSupply,
+        BelowMinSupply,
+        RouterCallFailed,
+        InvalidSchedule,
+        VestingExists,
+        SpenderRejected,
+        AlreadyGranted,
+        BalanceFrozen,
+        /// No longer returned; kept so that later discriminants stay stable.
+        AlreadyMigrated,
+        BalanceStaked,
+        FeeTooHigh,
+        NotOperator,
+        BatchTooLarge,
+        JurisdictionForbidden,
+        DustLeg,
+        NotSpender,
+        InvalidPeriod,
+        TooManyLocks,
+    }
+
+    impl Error {
+        /// Stable identifier for logging and mapping errors on the client side.
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                Error::BalanceTooLow => "balance_too_low",
+                Error::AllowanceTooLow => "allowance_too_low",
+                Error::NotOwner => "not_owner",
+                Error::SupplyFrozen => "supply_frozen",
+                Error::Overflow => "overflow",
+                Error::NotPoolMember => "not_pool_member",
+                Error::PoolExhausted => "pool_exhausted",
+                Error::InvalidFee => "invalid_fee",
+                Error::InvalidSignature => "invalid_signature",
+                Error::Expired => "expired",
+                Error::ZeroTransfer => "zero_transfer",
+                Error::TooManySpenders => "too_many_spenders",
+                Error::DelegatedPaused => "delegated_paused",
+                Error::InvalidBps => "invalid_bps",
+                Error::GlobalLimitExceeded => "global_limit_exceeded",
+                Error::ValidationFailed => "validation_failed",
+                Error::TooManyAccounts => "too_many_accounts",
+                Error::TicketNotFound => "ticket_not_found",
+                Error::TicketExpired => "ticket_expired",
+                Error::InvalidReason => "invalid_reason",
+                Error::NothingToClaim => "nothing_to_claim",
+                Error::DuplicateRequest => "duplicate_request",
+                Error::CallbackFailed => "callback_failed",
+                Error::Paused => "paused",
+                Error::InvalidThreshold => "invalid_threshold",
+                Error::NotGuardian => "not_guardian",
+                Error::RecoveryThresholdNotMet => "recovery_threshold_not_met",
+                Error::PriceFloorBreached => "price_floor_breached",
+                Error::InvalidRebase => "invalid_rebase",
+                Error::AboveBreakerThreshold => "above_breaker_threshold",
+                Error::AlreadyInitialized => "already_initialized",
+                Error::TradingNotStarted => "trading_not_started",
+                Error::TipFailed => "tip_failed",
+                Error::NotInitialized => "not_initialized",
+                Error::TimelockNotElapsed => "timelock_not_elapsed",
+                Error::ActionNotFound => "action_not_found",
+                Error::PeriodLimitExceeded => "period_limit_exceeded",
+                Error::SupplyMismatch => "supply_mismatch",
+                Error::DistributionPhaseActive => "distribution_phase_active",
+                Error::AboveMaxSupply => "above_max_supply",
+                Error::BelowMinSupply => "below_min_supply",
+                Error::RouterCallFailed => "router_call_failed",
+                Error::InvalidSchedule => "invalid_schedule",
+                Error::VestingExists => "vesting_exists",
+                Error::SpenderRejected => "spender_rejected",
+                Error::AlreadyGranted => "already_granted",
+                Error::BalanceFrozen => "balance_frozen",
+                Error::AlreadyMigrated => "already_migrated",
+                Error::BalanceStaked => "balance_staked",
+                Error::FeeTooHigh => "fee_too_high",
+                Error::NotOperator => "not_operator",
+                Error::BatchTooLarge => "batch_too_large",
+                Error::JurisdictionForbidden => "jurisdiction_forbidden",
+                Error::DustLeg => "dust_leg",
+                Error::NotSpender => "not_spender",
+                Error::InvalidPeriod => "invalid_period",
+                Error::TooManyLocks => "too_many_locks",
+            }
+        }
+    }
+
+    /// Snapshot of all configurable settings, returned by `config`.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Config {
+        pub owner: Option<AccountId>,
+        pub supply_frozen: bool,
+        pub fee_bps: u16,
+        pub treasury: Option<AccountId>,
+        pub round_up_fees: bool,
+        pub allow_zero_transfers: bool,
+        pub max_spenders: Option<u32>,
+        pub delegated_paused: bool,
+        pub outflow_limit: Option<(u16, Timestamp)>,
+        pub validator: Option<AccountId>,
+        pub max_known_accounts: u32,
+        pub ticket_ttl: BlockNumber,
+        pub quadratic_voting: bool,
+        pub paused: bool,
+        pub breaker_threshold: Option<Balance>,
+        pub oracle: Option<AccountId>,
+        pub min_price: u128,
+        pub trading_enabled_at: Timestamp,
+        pub rich_events: bool,
+        pub min_delay: Timestamp,
+        pub distribution_phase: bool,
+        pub min_supply: Option<Balance>,
+        pub max_supply: Option<Balance>,
+        pub router: Option<AccountId>,
+        pub verify_spenders: bool,
+        pub chain_id: u32,
+        pub seed_new_holders: bool,
+        pub seed_amount: Balance,
+        pub restrict_unset_jurisdictions: bool,
+        pub drop_dust: bool,
+        pub dust_threshold: Balance,
+    }
+
+    /// Result of `try_transfer`, which reports failures instead of reverting.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum TransferOutcome {
+        Success,
+        Failed(Error),
+        /// The transfer tripped the circuit breaker and nothing moved.
+        BreakerTripped,
+    }
+
+    /// Owner operations that must go through `queue_action` and
+    /// `execute_action` once `min_delay` is non-zero.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum AdminAction {
+        Mint(AccountId, Balance),
+        SetFee(u16, AccountId),
+        Pause,
+        SetMinDelay(Timestamp),
+        AuthorizeMint(AccountId, Balance),
+        Rebase(i128),
+        SetRoundUpFees(bool),
+    }
+
+    /// Outcome of `transfer_receipt`, combining what is otherwise spread over
+    /// events and separate queries.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct TransferReceipt {
+        pub from: AccountId,
+        pub to: AccountId,
+        pub gross: Balance,
+        pub net: Balance,
+        pub fee: Balance,
+        pub timestamp: Timestamp,
     }
 
+    /// `tag` identifies the token, so that one subscriber can filter the
+    /// transfers of several tokens sharing this event shape.
+    ///
+    /// Breaking change in logic version 2: `tag` took the topic slot of
+    /// `value`, since an event has at most four topics including its
+    /// signature. Indexers that filtered on `value` must read it from the
+    /// event data instead.
     #[ink(event)]
     pub struct Transfer {
         #[ink(topic)]
@@ -29,7 +481,9 @@ mod erc20 {
         #[ink(topic)]
         to: AccountId,
         #[ink(topic)]
+        tag: [u8; 8],
         value: Balance,
+        seq: u64,
     }
 
     #[ink(event)]
@@ -42,26 +496,239 @@ mod erc20 {
         value: Balance,
     }
 
+    /// Emitted alongside the burn `Transfer` by `burn_with_reason`.
+    #[ink(event)]
+    pub struct Burn {
+        #[ink(topic)]
+        from: AccountId,
+        value: Balance,
+        #[ink(topic)]
+        reason: u8,
+    }
+
+    /// Emitted when a transfer above `breaker_threshold` pauses the contract.
+    #[ink(event)]
+    pub struct CircuitBreakerTripped {
+        #[ink(topic)]
+        from: AccountId,
+        to: AccountId,
+        value: Balance,
+        threshold: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Rebase {
+        supply_delta: i128,
+        total_supply: Balance,
+    }
+
+    /// Emitted after `Transfer` when `rich_events` is set, carrying the
+    /// balances both parties end up with. It lets indexers skip replaying
+    /// history at the cost of extra event data on every transfer.
+    #[ink(event)]
+    pub struct TransferWithBalances {
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        value: Balance,
+        from_balance: Balance,
+        to_balance: Balance,
+    }
+
+    /// Emitted by `try_transfer` when the transfer could not be made.
+    #[ink(event)]
+    pub struct TransferFailed {
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        value: Balance,
+        reason: Error,
+    }
+
+    /// Emitted by `checkpoint_supply` when the summed shares disagree with
+    /// `total_shares`.
+    #[ink(event)]
+    pub struct SupplyMismatch {
+        expected: Balance,
+        actual: Balance,
+    }
+
+    /// Emitted after `Transfer` by `transfer_from`, recording the spender
+    /// that moved the tokens on `from`'s behalf.
+    #[ink(event)]
+    pub struct DelegatedTransfer {
+        #[ink(topic)]
+        spender: AccountId,
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        value: Balance,
+    }
+
+    /// Emitted by `transfer_from` when it decrements an allowance, so that
+    /// allowance use can be tracked apart from token movement.
+    #[ink(event)]
+    pub struct AllowanceSpent {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        amount: Balance,
+        remaining: Balance,
+    }
+
     type Result<T> = core::result::Result<T, Error>;
     impl Erc20 {
         /// Constructor that initializes the `bool` value to the given `init_value`.
         #[ink(constructor)]
         pub fn new(total_supply: Balance) -> Self {
-            let mut balances = Mapping::new();
             let sender = Self::env().caller();
-            balances.insert(&sender, &total_supply);
-            Self::env().emit_event(Transfer {
-                from: None,
-                to: sender,
-                value: total_supply,
-            });
+            Self::new_with_owner(total_supply, sender, sender)
+        }
+
+        /// Lets a deployer such as a factory contract hand ownership and the
+        /// initial supply to other accounts.
+        #[ink(constructor)]
+        pub fn new_with_owner(
+            total_supply: Balance,
+            owner: AccountId,
+            initial_holder: AccountId,
+        ) -> Self {
+            let mut instance = Self::new_uninitialized();
+            instance.setup(owner, Vec::from([(initial_holder, total_supply)]));
+            instance
+        }
+
+        /// Creates a contract with no supply and no owner, to be configured
+        /// afterwards by a single call to `initialize`.
+        #[ink(constructor)]
+        pub fn new_uninitialized() -> Self {
             Self {
-                total_supply,
-                balances,
+                storage_version: STORAGE_VERSION,
+                decimals: DEFAULT_DECIMALS,
+                allow_zero_transfers: true,
+                max_known_accounts: DEFAULT_MAX_KNOWN_ACCOUNTS,
+                ticket_ttl: DEFAULT_TICKET_TTL,
+                unit_scale: 1,
+                instantiator: Some(Self::env().caller()),
                 ..Default::default()
             }
         }
 
+        /// Mints `total_supply` to `owner` and makes it the owner. Can only be
+        /// called once, by the instantiator, and never on a contract built by
+        /// the other constructors.
+        #[ink(message)]
+        pub fn initialize(&mut self, total_supply: Balance, owner: AccountId) -> Result<()> {
+            if self.initialized {
+                return Err(Error::AlreadyInitialized);
+            }
+            if self.instantiator != Some(self.env().caller()) {
+                return Err(Error::NotOwner);
+            }
+            self.setup(owner, Vec::from([(owner, total_supply)]));
+            Ok(())
+        }
+
+        /// Distributes the initial supply to several holders at once, with the
+        /// caller as owner.
+        #[ink(constructor)]
+        pub fn new_with_allocations(allocations: Vec<(AccountId, Balance)>) -> Self {
+            let mut instance = Self::new_uninitialized();
+            instance.setup(Self::env().caller(), allocations);
+            instance
+        }
+
+        fn setup(&mut self, owner: AccountId, allocations: Vec<(AccountId, Balance)>) {
+            self.initialized = true;
+            self.owner = Some(owner);
+            for &(holder, value) in &allocations {
+                self.total_supply = self
+                    .total_supply
+                    .checked_add(value)
+                    .expect("initial supply overflows");
+                let shares = self.shares_of(holder);
+                self.set_shares(holder, shares + value);
+                if value > 0 {
+                    self.note_account(holder).expect("too many initial holders");
+                }
+                self.emit_transfer(None, holder, value);
+            }
+            self.total_shares = self.total_supply;
+            self.genesis.set(&allocations);
+        }
+
+        /// The initial allocations, in the order they were made.
+        #[ink(message)]
+        pub fn genesis_allocation(&self) -> Vec<(AccountId, Balance)> {
+            self.genesis.get().unwrap_or_default()
+        }
+
+        #[ink(constructor)]
+        pub fn new_with_metadata(
+            total_supply: Balance,
+            name: Option<String>,
+            symbol: Option<String>,
+            decimals: u8,
+        ) -> Self {
+            let mut instance = Self::new(total_supply);
+            instance.name = name;
+            instance.symbol = symbol;
+            instance.decimals = decimals;
+            instance
+        }
+
+        /// Like `new`, but tags every `Transfer` with `tag`.
+        #[ink(constructor)]
+        pub fn new_with_tag(total_supply: Balance, tag: [u8; 8]) -> Self {
+            let sender = Self::env().caller();
+            let mut instance = Self::new_uninitialized();
+            instance.tag = tag;
+            instance.setup(sender, Vec::from([(sender, total_supply)]));
+            instance
+        }
+
+        /// Like `new`, for a token pegged to a reference unit worth
+        /// `unit_scale` base units.
+        #[ink(constructor)]
+        pub fn new_with_unit_scale(total_supply: Balance, unit_scale: u128) -> Self {
+            assert!(unit_scale > 0, "unit scale must be positive");
+            let mut instance = Self::new(total_supply);
+            instance.unit_scale = unit_scale;
+            instance
+        }
+
+        /// Total supply in whole reference units, truncating any remainder.
+        #[ink(message)]
+        pub fn total_supply_in_units(&self) -> u128 {
+            self.total_supply
+                .checked_div(self.unit_scale)
+                .unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn tag(&self) -> [u8; 8] {
+            self.tag
+        }
+
+        #[ink(message)]
+        pub fn token_name(&self) -> Option<String> {
+            self.name.clone()
+        }
+
+        #[ink(message)]
+        pub fn token_symbol(&self) -> Option<String> {
+            self.symbol.clone()
+        }
+
+        #[ink(message)]
+        pub fn token_decimals(&self) -> u8 {
+            self.decimals
+        }
+
         #[ink(message)]
         pub fn total_supply(&self) -> Balance {
             self.total_supply
@@ -70,250 +737,6556 @@ mod erc20 {
         /// Simply returns the current value of our `bool`.
         #[ink(message)]
         pub fn balance_of(&self, who: AccountId) -> Balance {
-            self.balances.get(&who).unwrap_or_default()
+            self.to_amount(self.shares_of(who))
+        }
+
+        /// Number of `(outgoing, incoming)` transfers of `who`. Self-transfers
+        /// are not counted in either direction.
+        #[ink(message)]
+        pub fn transfer_counts(&self, who: AccountId) -> (u32, u32) {
+            (
+                self.transfer_count_out.get(who).unwrap_or_default(),
+                self.transfer_count_in.get(who).unwrap_or_default(),
+            )
+        }
+
+        /// Checks that the shares of `accounts` add up to `total_shares`. The
+        /// list must name every holder; repeated accounts are counted once.
+        /// Shares are compared rather than balances, which round down after a
+        /// rebase. On a mismatch this emits `SupplyMismatch` and fails; since
+        /// failing reverts the call, the event is only observable in a dry
+        /// run.
+        #[ink(message)]
+        pub fn checkpoint_supply(&mut self, accounts: Vec<AccountId>) -> Result<()> {
+            if accounts.len() > MAX_BATCH {
+                return Err(Error::BatchTooLarge);
+            }
+            let mut seen: Vec<AccountId> = Vec::new();
+            let mut actual: Balance = 0;
+            for who in accounts {
+                if seen.contains(&who) {
+                    continue;
+                }
+                seen.push(who);
+                actual = actual.saturating_add(self.shares_of(who));
+            }
+            if actual != self.total_shares {
+                self.env().emit_event(SupplyMismatch {
+                    expected: self.total_shares,
+                    actual,
+                });
+                return Err(Error::SupplyMismatch);
+            }
+            Ok(())
+        }
+
+        /// Balances of `accounts`, each paired with the account it belongs to.
+        #[ink(message)]
+        pub fn balances_labeled(
+            &self,
+            accounts: Vec<AccountId>,
+        ) -> Result<Vec<(AccountId, Balance)>> {
+            if accounts.len() > MAX_BATCH {
+                return Err(Error::BatchTooLarge);
+            }
+            Ok(accounts
+                .into_iter()
+                .map(|who| (who, self.balance_of(who)))
+                .collect())
+        }
+
+        /// Number of per-account storage entries held for `who`. Entries keyed
+        /// by pair, such as allowances and operators, cannot be enumerated, so
+        /// only the per-owner `spender_count` aggregate is counted for them.
+        /// Every mapping keyed by a single account belongs in this list.
+        #[ink(message)]
+        pub fn account_storage_slots(&self, who: AccountId) -> u32 {
+            [
+                self.balances.contains(who),
+                self.spender_count.contains(who),
+                self.nonces.contains(who),
+                self.bridge_accounts.contains(who),
+                self.known.contains(who),
+                self.mint_claims.contains(who),
+                self.guardians.contains(who),
+                self.block_locks.contains(who),
+                self.trading_whitelist.contains(who),
+                self.transfer_count_out.contains(who),
+                self.transfer_count_in.contains(who),
+                self.distribution_recipients.contains(who),
+                self.vesting.contains(who),
+                self.frozen.contains(who),
+                self.allowance_epoch.contains(who),
+                self.exempt.contains(who),
+                self.staked.contains(who),
+                self.jurisdictions.contains(who),
+            ]
+            .into_iter()
+            .map(u32::from)
+            .sum()
         }
 
+        /// Raw shares held by `who`, unaffected by rebases.
+        #[ink(message)]
+        pub fn shares_of(&self, who: AccountId) -> Balance {
+            self.balances.get(who).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn total_shares(&self) -> Balance {
+            self.total_shares
+        }
+
+        /// Voting weight of `who`: its balance, or the square root of its
+        /// balance when `quadratic_voting` is enabled. Vote counting should use
+        /// this rather than `balance_of` so the scheme can change later.
+        #[ink(message)]
+        pub fn voting_power(&self, who: AccountId) -> Balance {
+            let balance = self.balance_of(who);
+            if self.quadratic_voting {
+                isqrt(balance)
+            } else {
+                balance
+            }
+        }
+
+        /// A transfer above `breaker_threshold` returns `Ok` without moving
+        /// anything, so that the pause it triggers is kept. Check `paused` or
+        /// look for `CircuitBreakerTripped` to tell it apart.
         #[ink(message)]
         pub fn transfer(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            self.ensure_initialized()?;
             let sender = self.env().caller();
+            if self.trip_breaker(&sender, &to, value)? {
+                return Ok(());
+            }
             return self.transfer_from_to(&sender, &to, value);
         }
 
+        /// Transfers like `transfer`, but fails with `FeeTooHigh` if the fee
+        /// would exceed `max_fee`, e.g. after a fee raise landing first.
         #[ink(message)]
-        pub fn transfer_from(
+        pub fn transfer_with_max_fee(
             &mut self,
-            from: AccountId,
             to: AccountId,
             value: Balance,
+            max_fee: Balance,
         ) -> Result<()> {
+            let (_, fee) = self.quote_transfer(value);
+            if fee > max_fee {
+                return Err(Error::FeeTooHigh);
+            }
+            self.transfer(to, value)
+        }
+
+        /// Transfers `whole_tokens` whole tokens, i.e. `whole_tokens` scaled by
+        /// `10^decimals` base units.
+        #[ink(message)]
+        pub fn transfer_whole(&mut self, to: AccountId, whole_tokens: u128) -> Result<()> {
+            let value = 10u128
+                .checked_pow(u32::from(self.decimals))
+                .and_then(|unit| whole_tokens.checked_mul(unit))
+                .ok_or(Error::Overflow)?;
+            self.transfer(to, value)
+        }
+
+        /// Transfers like `transfer`, but only once ever from the caller to
+        /// `to`, for non-repeatable distributions.
+        #[ink(message)]
+        pub fn grant(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            self.ensure_initialized()?;
             let sender = self.env().caller();
-            let allowance = self.allowances.get(&(from, sender)).unwrap_or_default();
-            if allowance < value {
-                return Err(Error::AllowanceTooLow);
+            if self.granted.contains((sender, to)) {
+                return Err(Error::AlreadyGranted);
             }
-            self.allowances
-                .insert(&(from, sender), &(allowance - value));
-            self.transfer_from_to(&from, &to, value)?;
+            self.granted.insert((sender, to), &true);
+            self.transfer_from_to(&sender, &to, value)
+        }
+
+        /// Allows `from` to `grant` to `to` again.
+        #[ink(message)]
+        pub fn reset_grant(&mut self, from: AccountId, to: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.granted.remove((from, to));
             Ok(())
         }
 
-        fn transfer_from_to(
+        /// Transfers like `transfer`, but a failure is returned as an outcome
+        /// and logged with `TransferFailed` instead of reverting the call, so
+        /// the event survives. A transfer that trips the circuit breaker
+        /// pauses the contract and is reported as `BreakerTripped`.
+        #[ink(message)]
+        pub fn try_transfer(&mut self, to: AccountId, value: Balance) -> TransferOutcome {
+            let from = self.env().caller();
+            // Every check runs before anything is written, so a failure here
+            // leaves no partial state behind.
+            let result = self
+                .ensure_initialized()
+                .and_then(|()| self.trip_breaker(&from, &to, value))
+                .and_then(|tripped| {
+                    if tripped {
+                        return Ok(true);
+                    }
+                    self.transfer_from_to(&from, &to, value).map(|()| false)
+                });
+            match result {
+                Ok(true) => TransferOutcome::BreakerTripped,
+                Ok(false) => TransferOutcome::Success,
+                Err(reason) => {
+                    self.env().emit_event(TransferFailed {
+                        from,
+                        to,
+                        value,
+                        reason,
+                    });
+                    TransferOutcome::Failed(reason)
+                }
+            }
+        }
+
+        /// Transfers like `transfer` and returns a receipt of the amounts moved.
+        #[ink(message)]
+        pub fn transfer_receipt(
             &mut self,
-            from: &AccountId,
-            to: &AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<TransferReceipt> {
+            self.ensure_initialized()?;
+            let from = self.env().caller();
+            // Dropped dust moves nothing, so its receipt reports nothing.
+            let gross = if self.is_dust(value) { 0 } else { value };
+            // A self-transfer is charged nothing and keeps the whole value.
+            let (net, fee) = if from == to {
+                (gross, 0)
+            } else {
+                self.quote_transfer(gross)
+            };
+            self.transfer_from_to(&from, &to, value)?;
+            Ok(TransferReceipt {
+                from,
+                to,
+                gross,
+                net,
+                fee,
+                timestamp: self.env().block_timestamp(),
+            })
+        }
+
+        /// Owner-only transfer that emits no `Transfer` event, for bulk internal
+        /// reconciliation. Indexers that rebuild balances from events will not
+        /// see these moves and must re-read `balance_of` for affected accounts.
+        #[ink(message)]
+        pub fn silent_transfer(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            let sender = self.env().caller();
+            self.move_tokens(&sender, &to, value, false)
+        }
+
+        /// Transfers like `transfer` but rejects a repeated `key` from the same
+        /// caller, so relayers can safely retry.
+        #[ink(message)]
+        pub fn transfer_idempotent(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            key: [u8; 32],
+        ) -> Result<()> {
+            self.ensure_initialized()?;
+            let sender = self.env().caller();
+            if self.used_keys.contains((sender, key)) {
+                return Err(Error::DuplicateRequest);
+            }
+            self.used_keys.insert((sender, key), &true);
+            self.transfer_from_to(&sender, &to, value)
+        }
+
+        /// Sends `primary_bps` basis points of `value` to `primary` and the
+        /// remainder to `secondary`, so the two legs always add up to `value`.
+        #[ink(message)]
+        pub fn transfer_split(
+            &mut self,
+            primary: AccountId,
+            secondary: AccountId,
             value: Balance,
+            primary_bps: u16,
         ) -> Result<()> {
-            let balance_from = self.balance_of(*from);
-            let balance_to = self.balance_of(*to);
-            if value > balance_from {
+            self.ensure_initialized()?;
+            if primary_bps > MAX_BPS {
+                return Err(Error::InvalidBps);
+            }
+            let sender = self.env().caller();
+            if value > self.balance_of(sender) {
                 return Err(Error::BalanceTooLow);
             }
-            self.balances.insert(from, &(balance_from - value));
-            self.balances.insert(to, &(balance_to + value));
-            self.env().emit_event({
-                Transfer {
-                    from: Some(*from),
-                    to: *to,
-                    value,
-                }
-            });
+            let primary_value = bps_of(value, primary_bps);
+            // Unless the whole split is dust, neither leg may be dropped.
+            let dropped = |leg: Balance| leg > 0 && self.is_dust(leg);
+            if !self.is_dust(value) && (dropped(primary_value) || dropped(value - primary_value)) {
+                return Err(Error::DustLeg);
+            }
+            self.transfer_from_to(&sender, &primary, primary_value)?;
+            self.transfer_from_to(&sender, &secondary, value - primary_value)
+        }
+
+        /// Records the intent to transfer `value` to `to` without moving funds.
+        /// Returns a ticket id the caller can pass to `execute_transfer` within
+        /// `ticket_ttl` blocks.
+        #[ink(message)]
+        pub fn prepare_transfer(&mut self, to: AccountId, value: Balance) -> Result<u64> {
+            self.ensure_initialized()?;
+            let sender = self.env().caller();
+            let ticket = self.next_ticket;
+            self.next_ticket += 1;
+            self.tickets
+                .insert(ticket, &(sender, to, value, self.env().block_number()));
+            Ok(ticket)
+        }
+
+        #[ink(message)]
+        pub fn execute_transfer(&mut self, ticket: u64) -> Result<()> {
+            self.ensure_initialized()?;
+            let sender = self.env().caller();
+            let (from, to, value, prepared_at) = self
+                .tickets
+                .get(ticket)
+                .filter(|(from, ..)| *from == sender)
+                .ok_or(Error::TicketNotFound)?;
+            if self.env().block_number() > prepared_at.saturating_add(self.ticket_ttl) {
+                return Err(Error::TicketExpired);
+            }
+            self.tickets.remove(ticket);
+            self.transfer_from_to(&from, &to, value)
+        }
 
+        /// Transfers `value` tokens to `to` and forwards the attached native
+        /// value to it as a tip. Fails, undoing the token transfer, if the tip
+        /// cannot be delivered.
+        #[ink(message, payable)]
+        pub fn transfer_with_tip(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            self.ensure_initialized()?;
+            let from = self.env().caller();
+            self.transfer_from_to(&from, &to, value)?;
+            let tip = self.env().transferred_value();
+            if tip > 0 {
+                self.env().transfer(to, tip).map_err(|_| Error::TipFailed)?;
+            }
             Ok(())
         }
 
+        /// Moves `value` from the caller into the contract's custody, claimable
+        /// by `to` via `claim_by_block` once `unlock_block` is reached. Locks
+        /// sharing an unlock block are merged, and an account holds at most
+        /// `MAX_BATCH` of them, so that nobody can grow another account's lock
+        /// list past what its claims can load.
         #[ink(message)]
-        pub fn approve(&mut self, to: AccountId, value: Balance) -> Result<()> {
+        pub fn locked_transfer_by_block(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            unlock_block: BlockNumber,
+        ) -> Result<()> {
+            self.ensure_initialized()?;
+            let mut locks = self.block_locks.get(to).unwrap_or_default();
+            let existing = locks.iter().position(|(_, block)| *block == unlock_block);
+            if existing.is_none() && locks.len() >= MAX_BATCH {
+                return Err(Error::TooManyLocks);
+            }
             let sender = self.env().caller();
-            self.allowances.insert(&(sender, to), &value);
-            self.env().emit_event(Approve {
-                from: sender,
-                to,
-                value,
-            });
+            let received = self.move_into_custody(sender, value)?;
+            if received > 0 {
+                match existing {
+                    Some(index) => locks[index].0 += received,
+                    None => locks.push((received, unlock_block)),
+                }
+                self.block_locks.insert(to, &locks);
+            }
             Ok(())
         }
 
+        /// Releases every lock of the caller whose unlock block has been
+        /// reached, returning the amount released.
         #[ink(message)]
-        pub fn allowance(&self, from: AccountId, to: AccountId) -> Balance {
-            self.allowances.get(&(from, to)).unwrap_or_default()
+        pub fn claim_by_block(&mut self) -> Result<Balance> {
+            self.ensure_initialized()?;
+            let sender = self.env().caller();
+            let now = self.env().block_number();
+            let (ready, pending): (Vec<_>, Vec<_>) = self
+                .block_locks
+                .get(sender)
+                .unwrap_or_default()
+                .into_iter()
+                .partition(|(_, unlock_block)| *unlock_block <= now);
+            let shares: Balance = ready.iter().map(|(shares, _)| shares).sum();
+            if shares == 0 {
+                return Err(Error::NothingToClaim);
+            }
+            if pending.is_empty() {
+                self.block_locks.remove(sender);
+            } else {
+                self.block_locks.insert(sender, &pending);
+            }
+            self.release_from_custody(sender, shares)
         }
-    }
 
-    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
-    /// module and test functions are marked with a `#[test]` attribute.
-    /// The below code is technically just normal Rust code.
-    #[cfg(test)]
-    mod tests {
-        /// Imports all the definitions from the outer scope so we can use them here.
+        /// Moves `value` from the caller into the contract's custody, vesting
+        /// linearly to `to` between `start` and `end`. An account can only have
+        /// one schedule at a time, so only the owner may create one for
+        /// another account; anyone else could block its `mint_vested`.
+        #[ink(message)]
+        pub fn vest(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            start: Timestamp,
+            end: Timestamp,
+        ) -> Result<()> {
+            self.ensure_initialized()?;
+            let sender = self.env().caller();
+            if to != sender {
+                self.ensure_owner()?;
+            }
+            self.check_schedule(to, start, end)?;
+            let received = self.move_into_custody(sender, value)?;
+            if received > 0 {
+                self.vesting.insert(to, &(received, 0, start, end));
+            }
+            Ok(())
+        }
+
+        /// Mints `amount` new tokens straight into a vesting schedule for
+        /// `to`, unlocking linearly between `start` and `end`. Only callable
+        /// by the owner.
+        #[ink(message)]
+        pub fn mint_vested(
+            &mut self,
+            to: AccountId,
+            amount: Balance,
+            start: Timestamp,
+            end: Timestamp,
+        ) -> Result<()> {
+            self.ensure_initialized()?;
+            self.ensure_owner()?;
+            self.ensure_no_timelock()?;
+            self.check_schedule(to, start, end)?;
+            let custody = self.env().account_id();
+            let shares_before = self.shares_of(custody);
+            self.mint_to(custody, amount)?;
+            let minted = self.shares_of(custody) - shares_before;
+            if minted > 0 {
+                self.vesting.insert(to, &(minted, 0, start, end));
+            }
+            Ok(())
+        }
+
+        fn check_schedule(&self, to: AccountId, start: Timestamp, end: Timestamp) -> Result<()> {
+            if end <= start {
+                return Err(Error::InvalidSchedule);
+            }
+            if self.vesting.contains(to) {
+                return Err(Error::VestingExists);
+            }
+            Ok(())
+        }
+
+        /// Lets `operator` move any amount of the caller's tokens through
+        /// `transfer_from`, bypassing allowances, until revoked.
+        #[ink(message)]
+        pub fn set_operator(&mut self, operator: AccountId, approved: bool) -> Result<()> {
+            self.ensure_initialized()?;
+            let sender = self.env().caller();
+            if approved {
+                self.operators.insert((sender, operator), &true);
+            } else {
+                self.operators.remove((sender, operator));
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn is_operator(&self, owner: AccountId, operator: AccountId) -> bool {
+            self.operators.get((owner, operator)).unwrap_or_default()
+        }
+
+        /// Exchanges the entire balances of `a` and `b`. The caller must be, or
+        /// be an operator of, both accounts, and each leg must pass the same
+        /// checks as a regular transfer.
+        #[ink(message)]
+        pub fn swap_balances(&mut self, a: AccountId, b: AccountId) -> Result<()> {
+            self.ensure_initialized()?;
+            let sender = self.env().caller();
+            let authorized = |who: AccountId| who == sender || self.is_operator(who, sender);
+            if !authorized(a) || !authorized(b) {
+                return Err(Error::NotOperator);
+            }
+            if a == b {
+                return Ok(());
+            }
+            let (shares_a, shares_b) = (self.shares_of(a), self.shares_of(b));
+            let (amount_a, amount_b) = (self.to_amount(shares_a), self.to_amount(shares_b));
+            let legs = [(a, b, amount_a), (b, a, amount_b)];
+            for (from, to, amount) in legs {
+                let exempt = self.is_exempt(from);
+                if self.paused && !exempt {
+                    return Err(Error::Paused);
+                }
+                if from != sender && self.delegated_paused && !exempt {
+                    return Err(Error::DelegatedPaused);
+                }
+                if amount > 0 {
+                    self.transfer_restriction(&from, &to, amount)?;
+                }
+            }
+            for (from, _, amount) in legs {
+                self.record_outflow(&from, amount)?;
+            }
+            self.set_shares(a, shares_b);
+            self.set_shares(b, shares_a);
+            if amount_a > 0 {
+                self.note_account(b)?;
+                self.emit_transfer(Some(a), b, amount_a);
+            }
+            if amount_b > 0 {
+                self.note_account(a)?;
+                self.emit_transfer(Some(b), a, amount_b);
+            }
+            Ok(())
+        }
+
+        /// Stakes `amount` of the caller's transferable tokens, which stay in
+        /// their balance but cannot be moved until unstaked.
+        #[ink(message)]
+        pub fn stake(&mut self, amount: Balance) -> Result<()> {
+            self.ensure_initialized()?;
+            let sender = self.env().caller();
+            let staked = self.staked_of(sender);
+            let available = self
+                .balance_of(sender)
+                .saturating_sub(self.frozen_of(sender))
+                .saturating_sub(staked);
+            if amount > available {
+                return Err(Error::BalanceTooLow);
+            }
+            self.staked.insert(sender, &(staked + amount));
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn unstake(&mut self, amount: Balance) -> Result<()> {
+            self.ensure_initialized()?;
+            let sender = self.env().caller();
+            let staked = self
+                .staked_of(sender)
+                .checked_sub(amount)
+                .ok_or(Error::BalanceTooLow)?;
+            if staked == 0 {
+                self.staked.remove(sender);
+            } else {
+                self.staked.insert(sender, &staked);
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn staked_of(&self, who: AccountId) -> Balance {
+            self.staked.get(who).unwrap_or_default()
+        }
+
+        /// Amount of `who`'s schedule vested so far, including what has
+        /// already been released.
+        #[ink(message)]
+        pub fn vested_amount(&self, who: AccountId) -> Balance {
+            self.to_amount(self.vested_shares(who))
+        }
+
+        fn vested_shares(&self, who: AccountId) -> Balance {
+            let Some((total, _, start, end)) = self.vesting.get(who) else {
+                return 0;
+            };
+            let now = self.env().block_timestamp();
+            if now <= start {
+                0
+            } else if now >= end {
+                total
+            } else {
+                mul_div(
+                    total,
+                    Balance::from(now - start),
+                    Balance::from(end - start),
+                )
+            }
+        }
+
+        /// Releases whatever has vested for the caller since the last release.
+        #[ink(message)]
+        pub fn release_vested(&mut self) -> Result<Balance> {
+            self.ensure_initialized()?;
+            let sender = self.env().caller();
+            let (total, released, start, end) =
+                self.vesting.get(sender).ok_or(Error::NothingToClaim)?;
+            let vested = self.vested_shares(sender);
+            if vested == released {
+                return Err(Error::NothingToClaim);
+            }
+            if vested == total {
+                self.vesting.remove(sender);
+            } else {
+                self.vesting.insert(sender, &(total, vested, start, end));
+            }
+            self.release_from_custody(sender, vested - released)
+        }
+
+        /// Liquid balance plus everything held in custody for `who`: pending
+        /// block locks and unreleased vesting.
+        #[ink(message)]
+        pub fn total_balance_of(&self, who: AccountId) -> Balance {
+            let locked: Balance = self
+                .block_locks
+                .get(who)
+                .unwrap_or_default()
+                .iter()
+                .map(|(shares, _)| shares)
+                .sum();
+            let vesting = self
+                .vesting
+                .get(who)
+                .map_or(0, |(total, released, ..)| total - released);
+            self.balance_of(who) + self.to_amount(locked + vesting)
+        }
+
+        /// Transfers `value` from `from` into the contract's custody and returns
+        /// the shares that actually arrived, which is less than `value` after
+        /// fees.
+        fn move_into_custody(&mut self, from: AccountId, value: Balance) -> Result<Balance> {
+            let custody = self.env().account_id();
+            let shares_before = self.shares_of(custody);
+            self.transfer_from_to(&from, &custody, value)?;
+            Ok(self.shares_of(custody) - shares_before)
+        }
+
+        /// Pays `shares` out of the contract's custody to `to`.
+        fn release_from_custody(&mut self, to: AccountId, shares: Balance) -> Result<Balance> {
+            let custody = self.env().account_id();
+            let amount = self.to_amount(shares);
+            self.note_account(to)?;
+            let shares_custody = self.shares_of(custody);
+            self.set_shares(custody, shares_custody - shares);
+            let shares_to = self.shares_of(to);
+            self.set_shares(to, shares_to + shares);
+            self.emit_transfer(Some(custody), to, amount);
+            Ok(amount)
+        }
+
+        #[ink(message)]
+        pub fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            self.ensure_initialized()?;
+            let exempt = self.is_exempt(from);
+            if self.paused && !exempt {
+                return Err(Error::Paused);
+            }
+            if self.delegated_paused && !exempt {
+                return Err(Error::DelegatedPaused);
+            }
+            let sender = self.env().caller();
+            // A zero-value transfer is still a valid transfer per spec and emits
+            // the event, but it must not require or touch any allowance.
+            if value == 0 {
+                if !self.allow_zero_transfers {
+                    return Err(Error::ZeroTransfer);
+                }
+                self.emit_transfer(Some(from), to, value);
+                return Ok(());
+            }
+            // An allowance may exceed what the owner still holds, e.g. after a
+            // burn. Fail on the balance before any allowance is spent.
+            if value > self.balance_of(from) {
+                return Err(Error::BalanceTooLow);
+            }
+            // Authorize without writing anything, move the tokens, and only
+            // then spend the allowance, so that a transfer failing its checks
+            // or tripping the breaker leaves the allowance untouched.
+            let period_spend = if self.is_operator(from, sender) {
+                // Operators are not limited by any allowance.
+                None
+            } else if self.period_allowance(from, sender).is_some() {
+                Some(self.period_allowance_after(from, sender, value)?)
+            } else {
+                if self.allowance(from, sender) < value {
+                    return Err(Error::AllowanceTooLow);
+                }
+                None
+            };
+            if self.trip_breaker(&from, &to, value)? {
+                return Ok(());
+            }
+            // Dropped dust is still authorized above, but must not cost the
+            // owner any allowance.
+            if self.is_dust(value) {
+                return self.transfer_from_to(&from, &to, value);
+            }
+            self.transfer_from_to(&from, &to, value)?;
+            if let Some(entry) = period_spend {
+                self.period_allowances
+                    .insert(self.period_key(from, sender), &entry);
+            } else if !self.is_operator(from, sender) {
+                let allowance = self.allowance(from, sender);
+                // An allowance of `Balance::MAX` is treated as infinite and never
+                // decremented. The check above is against the gross `value`, fees
+                // included. The remaining allowance is announced so that it can be
+                // tracked from events alone.
+                if allowance != Balance::MAX {
+                    self.set_allowance(from, sender, allowance - value)?;
+                    self.env().emit_event(Approve {
+                        from,
+                        to: sender,
+                        value: allowance - value,
+                    });
+                    self.env().emit_event(AllowanceSpent {
+                        owner: from,
+                        spender: sender,
+                        amount: value,
+                        remaining: allowance - value,
+                    });
+                }
+            }
+            // Self-transfers emit no `Transfer`, so there is nothing to annotate.
+            if from != to {
+                self.env().emit_event(DelegatedTransfer {
+                    spender: sender,
+                    from,
+                    to,
+                    value,
+                });
+            }
+            Ok(())
+        }
+
+        /// Like `transfer_from`, but fails with `Expired` once `deadline` has
+        /// passed, so that a relayed call cannot execute unexpectedly late.
+        #[ink(message)]
+        pub fn transfer_from_by(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            deadline: Timestamp,
+        ) -> Result<()> {
+            if self.env().block_timestamp() > deadline {
+                return Err(Error::Expired);
+            }
+            self.transfer_from(from, to, value)
+        }
+
+        /// Spends the caller's allowance from each `from` in `transfers`. Totals
+        /// per owner are checked against balances and allowances before
+        /// anything moves; any later failure reverts the whole batch.
+        #[ink(message)]
+        pub fn transfer_from_batch(
+            &mut self,
+            transfers: Vec<(AccountId, AccountId, Balance)>,
+        ) -> Result<()> {
+            self.ensure_initialized()?;
+            if transfers.len() > MAX_BATCH {
+                return Err(Error::BatchTooLarge);
+            }
+            let sender = self.env().caller();
+            let mut totals: Vec<(AccountId, Balance)> = Vec::new();
+            for (from, _, value) in &transfers {
+                match totals.iter_mut().find(|(owner, _)| owner == from) {
+                    Some((_, total)) => {
+                        *total = total.checked_add(*value).ok_or(Error::Overflow)?
+                    }
+                    None => totals.push((*from, *value)),
+                }
+            }
+            for (from, total) in totals {
+                if total > self.balance_of(from) {
+                    return Err(Error::BalanceTooLow);
+                }
+                if !self.is_operator(from, sender) && total > self.allowance(from, sender) {
+                    return Err(Error::AllowanceTooLow);
+                }
+            }
+            for (from, to, value) in transfers {
+                self.transfer_from(from, to, value)?;
+            }
+            Ok(())
+        }
+
+        fn transfer_from_to(
+            &mut self,
+            from: &AccountId,
+            to: &AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            self.move_tokens(from, to, value, true)
+        }
+
+        /// Moves `value` from `from` to `to`, emitting `Transfer` events only
+        /// if `emit` is set.
+        fn move_tokens(
+            &mut self,
+            from: &AccountId,
+            to: &AccountId,
+            value: Balance,
+            emit: bool,
+        ) -> Result<()> {
+            if self.paused && !self.is_exempt(*from) {
+                return Err(Error::Paused);
+            }
+            self.check_transfer(from, to, value)?;
+            // Only `transfer`, `transfer_from` and `try_transfer` trip the
+            // breaker; every other path just rejects the transfer.
+            if !self.is_exempt(*from) && self.would_trip_breaker(value) {
+                return Err(Error::AboveBreakerThreshold);
+            }
+            // Nothing moves in a self-transfer, so no fee is charged and no
+            // `Transfer` is emitted once the checks have passed. Dust is
+            // dropped the same way.
+            if from == to || self.is_dust(value) {
+                return Ok(());
+            }
+            // Only an account's very first receipt is seeded. Checking the
+            // balance instead would reseed accounts that emptied themselves.
+            let new_holder = value > 0 && !self.known.contains(*to);
+            if value > 0 {
+                self.note_account(*to)?;
+            }
+            self.record_outflow(from, value)?;
+            let shares_from = self.shares_of(*from);
+            let balance_from = self.to_amount(shares_from);
+            let shares = if value == balance_from {
+                shares_from
+            } else {
+                self.to_shares(value).min(shares_from)
+            };
+            self.set_shares(*from, shares_from - shares);
+            let (net, fee) = self.quote_transfer(value);
+            let fee_shares = self.to_shares(fee).min(shares);
+            // Read the recipient only after debiting the sender so that a
+            // self-transfer does not credit a stale balance.
+            let shares_to = self.shares_of(*to);
+            self.set_shares(*to, shares_to + shares - fee_shares);
+            let count_out = self.transfer_count_out.get(from).unwrap_or_default();
+            self.transfer_count_out
+                .insert(from, &count_out.saturating_add(1));
+            let count_in = self.transfer_count_in.get(to).unwrap_or_default();
+            self.transfer_count_in
+                .insert(to, &count_in.saturating_add(1));
+            if emit {
+                self.emit_transfer(Some(*from), *to, net);
+            }
+            if let Some(treasury) = self.treasury.filter(|_| fee > 0) {
+                self.note_account(treasury)?;
+                let shares_treasury = self.shares_of(treasury);
+                self.set_shares(treasury, shares_treasury + fee_shares);
+                if emit {
+                    self.emit_transfer(Some(*from), treasury, fee);
+                }
+            }
+            // Seeding is best effort: a contract out of native funds must
+            // not block transfers. Custody moves to the contract are skipped.
+            if new_holder && net > 0 && self.seed_new_holders && *to != self.env().account_id() {
+                let _ = self.env().transfer(*to, self.seed_amount);
+            }
+            if emit && self.rich_events {
+                self.env().emit_event(TransferWithBalances {
+                    from: *from,
+                    to: *to,
+                    value: net,
+                    from_balance: self.balance_of(*from),
+                    to_balance: self.balance_of(*to),
+                });
+            }
+
+            Ok(())
+        }
+
+        /// Every check `move_tokens` makes before moving funds, other than the
+        /// pause and the circuit breaker. Shared with
+        /// `detect_transfer_restriction` so the two cannot disagree.
+        fn check_transfer(&self, from: &AccountId, to: &AccountId, value: Balance) -> Result<()> {
+            if value == 0 && !self.allow_zero_transfers {
+                return Err(Error::ZeroTransfer);
+            }
+            self.check_spendable(from, value)?;
+            if !self.is_exempt(*from) {
+                self.check_policy(from, to, value)?;
+            }
+            let (_, fee) = self.quote_transfer(value);
+            let treasury = self.treasury.filter(|treasury| fee > 0 && treasury != to);
+            let new_accounts = [Some(*to).filter(|_| value > 0), treasury]
+                .into_iter()
+                .flatten()
+                .filter(|who| !self.known.contains(who))
+                .count();
+            // Known accounts are never forgotten, so the counter is their
+            // number without decoding the whole list.
+            if self.total_accounts_ever as usize + new_accounts > self.max_known_accounts as usize {
+                return Err(Error::TooManyAccounts);
+            }
+            Ok(())
+        }
+
+        /// Whether `who` holds `value` tokens it may move or burn.
+        fn check_spendable(&self, who: &AccountId, value: Balance) -> Result<()> {
+            let balance = self.balance_of(*who);
+            if value > balance {
+                return Err(Error::BalanceTooLow);
+            }
+            let unfrozen = balance.saturating_sub(self.frozen_of(*who));
+            if value > unfrozen {
+                return Err(Error::BalanceFrozen);
+            }
+            if value > unfrozen.saturating_sub(self.staked_of(*who)) {
+                return Err(Error::BalanceStaked);
+            }
+            Ok(())
+        }
+
+        /// Launch, distribution, validator, price and outflow restrictions,
+        /// none of which apply to exempt senders.
+        fn check_policy(&self, from: &AccountId, to: &AccountId, value: Balance) -> Result<()> {
+            if self.env().block_timestamp() < self.trading_enabled_at
+                && !self.is_owner(*from)
+                && !self.trading_whitelist.get(from).unwrap_or_default()
+            {
+                return Err(Error::TradingNotStarted);
+            }
+            if self.distribution_phase
+                && (!(self.is_owner(*from) || self.treasury == Some(*from))
+                    || !self.distribution_recipients.get(to).unwrap_or_default())
+            {
+                return Err(Error::DistributionPhaseActive);
+            }
+            self.check_jurisdictions(from, to)?;
+            self.validate_transfer(from, to, value)?;
+            self.check_price_floor()?;
+            self.projected_outflow(from, value)?;
+            Ok(())
+        }
+
+        fn check_jurisdictions(&self, from: &AccountId, to: &AccountId) -> Result<()> {
+            match (self.jurisdictions.get(from), self.jurisdictions.get(to)) {
+                (Some(from_code), Some(to_code)) => {
+                    if self
+                        .forbidden_jurisdictions
+                        .get((from_code, to_code))
+                        .unwrap_or_default()
+                    {
+                        return Err(Error::JurisdictionForbidden);
+                    }
+                }
+                _ if self.restrict_unset_jurisdictions => {
+                    return Err(Error::JurisdictionForbidden);
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+
+        /// Returns `0` if a transfer of `value` from `from` to `to` would
+        /// currently succeed, or the code of the restriction preventing it.
+        /// Codes are the `Error` discriminant plus one, so they are as stable
+        /// as the error enum itself.
+        #[ink(message)]
+        pub fn detect_transfer_restriction(
+            &self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> u8 {
+            match self.transfer_restriction(&from, &to, value) {
+                Ok(()) => 0,
+                Err(error) => error as u8 + 1,
+            }
+        }
+
+        /// The error a transfer of `value` from `from` to `to` would currently
+        /// fail with, checked in the same order as the transfer itself.
+        #[ink(message)]
+        pub fn transfer_precheck(
+            &self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            self.ensure_initialized()?;
+            self.transfer_restriction(&from, &to, value)
+        }
+
+        /// `check_transfer` preceded by the pause and circuit breaker checks
+        /// that `move_tokens` makes first.
+        fn transfer_restriction(
+            &self,
+            from: &AccountId,
+            to: &AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            let exempt = self.is_exempt(*from);
+            if self.paused && !exempt {
+                return Err(Error::Paused);
+            }
+            self.check_transfer(from, to, value)?;
+            if !exempt && self.would_trip_breaker(value) {
+                return Err(Error::AboveBreakerThreshold);
+            }
+            Ok(())
+        }
+
+        /// Whether a transfer of `value` by a non-exempt sender would trip the
+        /// circuit breaker, so that large transfers can be split up front.
+        #[ink(message)]
+        pub fn would_trip_breaker(&self, value: Balance) -> bool {
+            self.breaker_threshold
+                .is_some_and(|threshold| value > threshold)
+        }
+
+        /// Projected balances of `from` and `to` after a transfer of `value`,
+        /// including any fee, without changing state. Assumes the transfer
+        /// goes through; see `detect_transfer_restriction` for whether it will.
+        #[ink(message)]
+        pub fn simulate_transfer(
+            &self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> (Balance, Balance) {
+            if from == to || self.is_dust(value) {
+                return (self.balance_of(from), self.balance_of(to));
+            }
+            let (net, fee) = self.quote_transfer(value);
+            let kept = |who: AccountId| if self.treasury == Some(who) { fee } else { 0 };
+            let from_balance = self.balance_of(from).saturating_sub(value);
+            (
+                from_balance + kept(from),
+                self.balance_of(to) + net + kept(to),
+            )
+        }
+
+        /// Human readable text for a `detect_transfer_restriction` code.
+        #[ink(message)]
+        pub fn message_for_restriction_code(&self, code: u8) -> String {
+            if code == 0 {
+                return String::from("no_restriction");
+            }
+            match <Error as scale::Decode>::decode(&mut &[code - 1][..]) {
+                Ok(error) => String::from(error.as_str()),
+                Err(_) => String::from("unknown_restriction"),
+            }
+        }
+
+        /// Pauses the contract and returns `true` if `value` exceeds the
+        /// breaker threshold. The offending transfer must then be skipped
+        /// while still returning `Ok`, since an `Err` would revert the pause
+        /// along with everything else. Only transfers that pass every other
+        /// check may trip it, so that an account without funds or allowance
+        /// cannot pause the token; any other failure is returned as is.
+        fn trip_breaker(
+            &mut self,
+            from: &AccountId,
+            to: &AccountId,
+            value: Balance,
+        ) -> Result<bool> {
+            match self.transfer_restriction(from, to, value) {
+                Err(Error::AboveBreakerThreshold) => {}
+                other => return other.map(|()| false),
+            }
+            let threshold = self.breaker_threshold.unwrap_or_default();
+            self.paused = true;
+            self.env().emit_event(CircuitBreakerTripped {
+                from: *from,
+                to: *to,
+                value,
+                threshold,
+            });
+            Ok(true)
+        }
+
+        /// Asks the registered validator contract, if any, whether the transfer
+        /// may proceed. A failing call counts as a rejection.
+        fn validate_transfer(
+            &self,
+            from: &AccountId,
+            to: &AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            let Some(validator) = self.validator else {
+                return Ok(());
+            };
+            let result = build_call::<DefaultEnvironment>()
+                .call(validator)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("validate")))
+                        .push_arg(from)
+                        .push_arg(to)
+                        .push_arg(value),
+                )
+                .returns::<bool>()
+                .try_invoke();
+            match result {
+                Ok(Ok(true)) => Ok(()),
+                _ => Err(Error::ValidationFailed),
+            }
+        }
+
+        /// Sends `value` to the configured router and asks it to deliver at
+        /// least `min_out` to `to`. The router delivers by calling back into
+        /// this contract, and the output is measured as the increase in `to`'s
+        /// balance. A failing call, or a lower output, reverts the whole
+        /// transfer.
+        #[ink(message)]
+        pub fn transfer_via_router(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            min_out: Balance,
+        ) -> Result<Balance> {
+            self.ensure_initialized()?;
+            let router = self.router.ok_or(Error::RouterCallFailed)?;
+            let from = self.env().caller();
+            self.transfer_from_to(&from, &router, value)?;
+            let before = self.balance_of(to);
+            // The router re-enters to deliver, so it must see our writes, and
+            // we must not write our stale root back over its writes after.
+            ink::env::set_contract_storage(&<Self as ink::storage::traits::StorageKey>::KEY, self);
+            let result = build_call::<DefaultEnvironment>()
+                .call(router)
+                .gas_limit(0)
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("route")))
+                        .push_arg(from)
+                        .push_arg(to)
+                        .push_arg(value),
+                )
+                .returns::<Balance>()
+                .try_invoke();
+            if let Ok(Some(reloaded)) =
+                ink::env::get_contract_storage(&<Self as ink::storage::traits::StorageKey>::KEY)
+            {
+                *self = reloaded;
+            }
+            let delivered = self.balance_of(to).saturating_sub(before);
+            match result {
+                Ok(Ok(_)) if delivered >= min_out => Ok(delivered),
+                _ => Err(Error::RouterCallFailed),
+            }
+        }
+
+        /// Asks a contract spender whether it accepts an allowance of `value`.
+        /// Spenders that are not contracts are not probed.
+        fn probe_spender(
+            &self,
+            owner: &AccountId,
+            spender: &AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            if !self.env().is_contract(spender) {
+                return Ok(());
+            }
+            let result = build_call::<DefaultEnvironment>()
+                .call(*spender)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("accepts_approval")))
+                        .push_arg(owner)
+                        .push_arg(value),
+                )
+                .returns::<bool>()
+                .try_invoke();
+            match result {
+                Ok(Ok(true)) => Ok(()),
+                _ => Err(Error::SpenderRejected),
+            }
+        }
+
+        /// Rejects transfers while the configured oracle, if any, reports a
+        /// price below `min_price`. A failing oracle call also halts transfers.
+        fn check_price_floor(&self) -> Result<()> {
+            let Some(oracle) = self.oracle else {
+                return Ok(());
+            };
+            let result = build_call::<DefaultEnvironment>()
+                .call(oracle)
+                .gas_limit(0)
+                .exec_input(ExecutionInput::new(Selector::new(ink::selector_bytes!(
+                    "price"
+                ))))
+                .returns::<u128>()
+                .try_invoke();
+            match result {
+                Ok(Ok(price)) if price >= self.min_price => Ok(()),
+                _ => Err(Error::PriceFloorBreached),
+            }
+        }
+
+        /// Counts `value` against the rolling outflow cap if `from` is a bridge
+        /// account, starting a new window once the current one has elapsed.
+        fn record_outflow(&mut self, from: &AccountId, value: Balance) -> Result<()> {
+            if let Some((window_start, outflow)) = self.projected_outflow(from, value)? {
+                self.outflow_window_start = window_start;
+                self.outflow_in_window = outflow;
+            }
+            Ok(())
+        }
+
+        /// The `(window start, outflow in window)` that `record_outflow` would
+        /// store for this transfer, or `None` if `from` is not capped.
+        fn projected_outflow(
+            &self,
+            from: &AccountId,
+            value: Balance,
+        ) -> Result<Option<(Timestamp, Balance)>> {
+            let Some((limit_bps, window)) = self.outflow_limit else {
+                return Ok(None);
+            };
+            if !self.bridge_accounts.get(from).unwrap_or_default() || self.is_exempt(*from) {
+                return Ok(None);
+            }
+            let now = self.env().block_timestamp();
+            let (window_start, in_window) =
+                if now >= self.outflow_window_start.saturating_add(window) {
+                    (now, 0)
+                } else {
+                    (self.outflow_window_start, self.outflow_in_window)
+                };
+            let limit = bps_of(self.total_supply, limit_bps);
+            let outflow = in_window.saturating_add(value);
+            if outflow > limit {
+                return Err(Error::GlobalLimitExceeded);
+            }
+            Ok(Some((window_start, outflow)))
+        }
+
+        /// Returns `(net, fee)` for a transfer of `value`. The fee is rounded
+        /// down unless `round_up_fees` is set; either way `net + fee == value`.
+        #[ink(message)]
+        pub fn quote_transfer(&self, value: Balance) -> (Balance, Balance) {
+            if self.treasury.is_none() {
+                return (value, 0);
+            }
+            let bps = Balance::from(self.fee_bps);
+            let denominator = Balance::from(MAX_BPS);
+            // Split `value` so that `value * bps` cannot overflow.
+            let whole = value / denominator * bps;
+            let rest = value % denominator * bps;
+            let fee = if self.round_up_fees {
+                whole + (rest + denominator - 1) / denominator
+            } else {
+                whole + rest / denominator
+            };
+            (value - fee, fee)
+        }
+
+        /// Stores an allowance while keeping `spender_count` in sync, enforcing
+        /// `max_spenders` when a new spender is granted a non-zero allowance.
+        /// A zero allowance removes the entry.
+        fn set_allowance(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            let previous = self.stored_allowance(owner, spender);
+            let count = self.spender_count(owner);
+            if previous == 0 && value > 0 {
+                if self.max_spenders.is_some_and(|max| count >= max) {
+                    return Err(Error::TooManySpenders);
+                }
+                self.spender_count.insert(owner, &(count + 1));
+            } else if previous > 0 && value == 0 {
+                self.spender_count.insert(owner, &(count - 1));
+            }
+            if value == 0 {
+                self.allowances.remove((owner, spender));
+                self.allowance_epochs.remove((owner, spender));
+            } else {
+                self.allowances.insert((owner, spender), &value);
+                match self.allowance_epoch(owner) {
+                    0 => self.allowance_epochs.remove((owner, spender)),
+                    epoch => {
+                        self.allowance_epochs.insert((owner, spender), &epoch);
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        /// The stored allowance, or `0` if it was granted before the owner's
+        /// last `revoke_all_allowances`.
+        fn stored_allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            let granted_in = self
+                .allowance_epochs
+                .get((owner, spender))
+                .unwrap_or_default();
+            if granted_in != self.allowance_epoch(owner) {
+                return 0;
+            }
+            self.allowances.get((owner, spender)).unwrap_or_default()
+        }
+
+        /// Appends `who` to `known_accounts` on its first receipt of tokens.
+        fn note_account(&mut self, who: AccountId) -> Result<()> {
+            if self.known.contains(who) {
+                return Ok(());
+            }
+            let mut known_accounts = self.known_accounts.get().unwrap_or_default();
+            if known_accounts.len() >= self.max_known_accounts as usize {
+                return Err(Error::TooManyAccounts);
+            }
+            known_accounts.push(who);
+            self.known_accounts.set(&known_accounts);
+            self.known.insert(who, &true);
+            self.total_accounts_ever += 1;
+            Ok(())
+        }
+
+        /// Stores `shares` for `who`, removing the entry entirely when it drops
+        /// to zero. `balance_of` still reports `0` for removed entries.
+        fn set_shares(&mut self, who: AccountId, shares: Balance) {
+            if shares == 0 {
+                self.balances.remove(who);
+            } else {
+                self.balances.insert(who, &shares);
+            }
+        }
+
+        fn to_shares(&self, amount: Balance) -> Balance {
+            if self.total_supply == 0 || self.total_shares == 0 {
+                return amount;
+            }
+            mul_div(amount, self.total_shares, self.total_supply)
+        }
+
+        fn to_amount(&self, shares: Balance) -> Balance {
+            if self.total_shares == 0 {
+                return shares;
+            }
+            mul_div(shares, self.total_supply, self.total_shares)
+        }
+
+        /// Emits a `Transfer` tagged with the next sequence number so indexers
+        /// can order transfers within a block.
+        fn emit_transfer(&mut self, from: Option<AccountId>, to: AccountId, value: Balance) {
+            self.transfer_seq += 1;
+            self.env().emit_event(Transfer {
+                from,
+                to,
+                tag: self.tag,
+                value,
+                seq: self.transfer_seq,
+            });
+        }
+
+        #[ink(message)]
+        pub fn approve(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            self.ensure_initialized()?;
+            let sender = self.env().caller();
+            self.approve_from(sender, to, value)
+        }
+
+        /// Sets `owner`'s allowance for `spender` to `value`, replacing any
+        /// expiry or period allowance. Every path that approves on an owner's
+        /// behalf, signed or not, goes through here.
+        fn approve_from(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            if value > 0 && self.verify_spenders {
+                self.probe_spender(&owner, &spender, value)?;
+            }
+            self.set_allowance(owner, spender, value)?;
+            self.allowance_expiry.remove((owner, spender));
+            self.clear_period_allowance(owner, spender);
+            self.record_allowance_history(owner, spender, value);
+            if value == 0 {
+                self.approved_at.remove((owner, spender));
+            } else {
+                self.approved_at
+                    .insert((owner, spender), &self.env().block_timestamp());
+            }
+            self.env().emit_event(Approve {
+                from: owner,
+                to: spender,
+                value,
+            });
+            Ok(())
+        }
+
+        /// Share of `owner`'s balance `spender` may move, in basis points. An
+        /// allowance above the balance counts as the whole balance; an empty
+        /// balance gives zero.
+        #[ink(message)]
+        pub fn allowance_ratio_bps(&self, owner: AccountId, spender: AccountId) -> u16 {
+            let balance = self.balance_of(owner);
+            if balance == 0 {
+                return 0;
+            }
+            let spendable = self.allowance(owner, spender).min(balance);
+            // At most `MAX_BPS`, so the conversion cannot fail.
+            u16::try_from(mul_div(spendable, Balance::from(MAX_BPS), balance)).unwrap_or(MAX_BPS)
+        }
+
+        /// When the current allowance of `spender` was last approved, or `None`
+        /// if there is no allowance.
+        #[ink(message)]
+        pub fn approval_age(&self, owner: AccountId, spender: AccountId) -> Option<Timestamp> {
+            if self.allowance(owner, spender) == 0 {
+                return None;
+            }
+            self.approved_at.get((owner, spender))
+        }
+
+        /// Sends `transfer_value` to `to` and sets `to`'s allowance over the
+        /// caller's tokens to `approve_value`.
+        #[ink(message)]
+        pub fn transfer_and_approve(
+            &mut self,
+            to: AccountId,
+            transfer_value: Balance,
+            approve_value: Balance,
+        ) -> Result<()> {
+            self.transfer(to, transfer_value)?;
+            self.approve(to, approve_value)
+        }
+
+        /// Approves `spender` and then calls its `on_approval(owner, value, data)`
+        /// message, failing unless it returns `true`. The spender may re-enter
+        /// this contract, e.g. to spend the allowance right away.
+        #[ink(message)]
+        pub fn approve_and_call(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> Result<()> {
+            self.approve(spender, value)?;
+            let owner = self.env().caller();
+            let result = build_call::<DefaultEnvironment>()
+                .call(spender)
+                .gas_limit(0)
+                .call_flags(CallFlags::default().set_allow_reentry(true))
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("on_approval")))
+                        .push_arg(owner)
+                        .push_arg(value)
+                        .push_arg(data),
+                )
+                .returns::<bool>()
+                .try_invoke();
+            // A re-entrant call has written its own copy of the root storage.
+            // Reload it so our stale copy is not written back over it.
+            if let Ok(Some(reloaded)) =
+                ink::env::get_contract_storage(&<Self as ink::storage::traits::StorageKey>::KEY)
+            {
+                *self = reloaded;
+            }
+            match result {
+                Ok(Ok(true)) => Ok(()),
+                _ => Err(Error::CallbackFailed),
+            }
+        }
+
+        /// Raises the caller's allowance for `to` by `delta`. Fails with
+        /// `Overflow` instead of wrapping or saturating at `Balance::MAX`.
+        #[ink(message)]
+        pub fn increase_allowance(&mut self, to: AccountId, delta: Balance) -> Result<()> {
+            let sender = self.env().caller();
+            let value = self
+                .allowance(sender, to)
+                .checked_add(delta)
+                .ok_or(Error::Overflow)?;
+            self.adjust_allowance(to, value)
+        }
+
+        /// Lowers the caller's allowance for `to` by `delta` and returns what
+        /// is left, failing with `AllowanceTooLow` if it is smaller than `delta`.
+        #[ink(message)]
+        pub fn decrease_allowance(&mut self, to: AccountId, delta: Balance) -> Result<Balance> {
+            let sender = self.env().caller();
+            let value = self
+                .allowance(sender, to)
+                .checked_sub(delta)
+                .ok_or(Error::AllowanceTooLow)?;
+            self.adjust_allowance(to, value)?;
+            Ok(value)
+        }
+
+        /// Approves `value` like `approve`, but keeps a pending expiry, so that
+        /// raising or lowering an expiring allowance does not make it
+        /// permanent. An allowance that already expired read as zero, so the
+        /// new value starts afresh without one.
+        fn adjust_allowance(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            let sender = self.env().caller();
+            let now = self.env().block_timestamp();
+            let expiry = self
+                .allowance_expiry
+                .get((sender, to))
+                .filter(|expires_at| now <= *expires_at);
+            self.approve(to, value)?;
+            if let Some(expires_at) = expiry {
+                self.allowance_expiry.insert((sender, to), &expires_at);
+            }
+            Ok(())
+        }
+
+        /// Clears the caller's allowance for `to` whatever its current value,
+        /// for integrators that want a decrease to saturate at zero.
+        #[ink(message)]
+        pub fn decrease_allowance_to_zero(&mut self, to: AccountId) -> Result<()> {
+            self.approve(to, 0)
+        }
+
+        /// Lets `spender` move up to `limit_per_period` of the caller's tokens in
+        /// each `period`, e.g. for subscriptions. While set, this replaces the
+        /// plain allowance for `transfer_from`. A zero limit removes it.
+        #[ink(message)]
+        pub fn approve_per_period(
+            &mut self,
+            spender: AccountId,
+            limit_per_period: Balance,
+            period: Timestamp,
+        ) -> Result<()> {
+            self.ensure_initialized()?;
+            let owner = self.env().caller();
+            if limit_per_period == 0 {
+                self.clear_period_allowance(owner, spender);
+            } else {
+                // A zero period would start a new window on every call, leaving
+                // the number of calls unlimited.
+                if period == 0 {
+                    return Err(Error::InvalidPeriod);
+                }
+                let now = self.env().block_timestamp();
+                self.period_allowances.insert(
+                    self.period_key(owner, spender),
+                    &(limit_per_period, period, 0, now),
+                );
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn period_allowance(
+            &self,
+            owner: AccountId,
+            spender: AccountId,
+        ) -> Option<(Balance, Timestamp, Balance, Timestamp)> {
+            self.period_allowances.get(self.period_key(owner, spender))
+        }
+
+        fn period_key(&self, owner: AccountId, spender: AccountId) -> (AccountId, AccountId, u64) {
+            (owner, spender, self.allowance_epoch(owner))
+        }
+
+        fn clear_period_allowance(&mut self, owner: AccountId, spender: AccountId) {
+            self.period_allowances
+                .remove(self.period_key(owner, spender));
+        }
+
+        /// The period allowance entry after `spender` moves `value` more of
+        /// `owner`'s tokens, failing if that exceeds the current period's limit.
+        fn period_allowance_after(
+            &self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+        ) -> Result<(Balance, Timestamp, Balance, Timestamp)> {
+            let (limit, period, spent, window_start) =
+                self.period_allowance(owner, spender).unwrap_or_default();
+            let now = self.env().block_timestamp();
+            let (spent, window_start) = if now >= window_start.saturating_add(period) {
+                (0, now)
+            } else {
+                (spent, window_start)
+            };
+            let spent = spent.saturating_add(value);
+            if spent > limit {
+                return Err(Error::PeriodLimitExceeded);
+            }
+            Ok((limit, period, spent, window_start))
+        }
+
+        /// Zeroes the caller's allowance to each of `spenders`.
+        #[ink(message)]
+        pub fn revoke_allowances(&mut self, spenders: Vec<AccountId>) -> Result<()> {
+            self.ensure_initialized()?;
+            if spenders.len() > MAX_BATCH {
+                return Err(Error::BatchTooLarge);
+            }
+            let owner = self.env().caller();
+            for spender in spenders {
+                self.approve_from(owner, spender, 0)?;
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn allowance(&self, from: AccountId, to: AccountId) -> Balance {
+            // A period allowance replaces the plain one; report what is left
+            // of it in the current period.
+            if self.period_allowance(from, to).is_some() {
+                return self
+                    .period_allowance_after(from, to, 0)
+                    .map(|(limit, _, spent, _)| limit - spent)
+                    .unwrap_or_default();
+            }
+            if let Some(expires_at) = self.allowance_expiry.get((from, to)) {
+                if self.env().block_timestamp() > expires_at {
+                    return 0;
+                }
+            }
+            self.stored_allowance(from, to)
+        }
+
+        #[ink(message)]
+        pub fn allowance_epoch(&self, owner: AccountId) -> u64 {
+            self.allowance_epoch.get(owner).unwrap_or_default()
+        }
+
+        /// Invalidates every allowance the caller has granted by starting a
+        /// new epoch.
+        #[ink(message)]
+        pub fn revoke_all_allowances(&mut self) -> Result<()> {
+            self.ensure_initialized()?;
+            let owner = self.env().caller();
+            let epoch = self
+                .allowance_epoch(owner)
+                .checked_add(1)
+                .ok_or(Error::Overflow)?;
+            self.allowance_epoch.insert(owner, &epoch);
+            self.spender_count.remove(owner);
+            Ok(())
+        }
+
+        /// The last value approved for `spender` in each of the most recent
+        /// epochs, oldest first.
+        #[ink(message)]
+        pub fn allowance_history(
+            &self,
+            owner: AccountId,
+            spender: AccountId,
+        ) -> Vec<(u64, Balance)> {
+            self.allowance_history
+                .get((owner, spender))
+                .unwrap_or_default()
+        }
+
+        fn record_allowance_history(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+        ) {
+            let epoch = self.allowance_epoch(owner);
+            let mut history = self.allowance_history(owner, spender);
+            match history.last_mut() {
+                Some(last) if last.0 == epoch => last.1 = value,
+                _ => {
+                    if history.len() >= MAX_ALLOWANCE_HISTORY {
+                        history.remove(0);
+                    }
+                    history.push((epoch, value));
+                }
+            }
+            self.allowance_history.insert((owner, spender), &history);
+        }
+
+        /// Approves `to` like `approve`, but the allowance reads as `0` once
+        /// the block timestamp passes `expires_at`.
+        #[ink(message)]
+        pub fn approve_with_expiry(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            expires_at: Timestamp,
+        ) -> Result<()> {
+            self.approve(to, value)?;
+            let sender = self.env().caller();
+            self.allowance_expiry.insert((sender, to), &expires_at);
+            Ok(())
+        }
+
+        /// Number of spenders `owner` currently has a non-zero allowance for.
+        #[ink(message)]
+        pub fn spender_count(&self, owner: AccountId) -> u32 {
+            self.spender_count.get(owner).unwrap_or_default()
+        }
+
+        /// Accounts that have ever received tokens, for off-chain
+        /// reconciliation since `balances` cannot be enumerated.
+        #[ink(message)]
+        pub fn all_known_accounts(&self) -> Vec<AccountId> {
+            self.known_accounts.get().unwrap_or_default()
+        }
+
+        /// Number of distinct accounts that have ever received tokens. Never
+        /// decreases, even when accounts are drained.
+        #[ink(message)]
+        pub fn total_accounts_ever(&self) -> u32 {
+            self.total_accounts_ever
+        }
+
+        /// Gas left in the current call. Only meaningful when queried through a
+        /// dry run, e.g. to tune batch sizes off-chain.
+        #[ink(message)]
+        pub fn remaining_gas(&self) -> u64 {
+            self.env().gas_left()
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> Option<AccountId> {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn is_zero_account(&self, who: AccountId) -> bool {
+            who == zero_account()
+        }
+
+        /// Whether `who` is the owner. Always `false` when there is no owner.
+        #[ink(message)]
+        pub fn is_owner(&self, who: AccountId) -> bool {
+            self.owner == Some(who)
+        }
+
+        /// Mints `value` new tokens to `to`. Only callable by the owner.
+        #[ink(message)]
+        pub fn mint(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            self.ensure_initialized()?;
+            self.ensure_owner()?;
+            self.ensure_no_timelock()?;
+            self.mint_to(to, value)
+        }
+
+        /// Mints `total_new` tokens across `holders` in proportion to their
+        /// current balances. Rounding dust goes to the largest holder, so
+        /// exactly `total_new` is minted. Repeated holders count once. Only
+        /// callable by the owner.
+        #[ink(message)]
+        pub fn mint_proportional(
+            &mut self,
+            total_new: Balance,
+            holders: Vec<AccountId>,
+        ) -> Result<()> {
+            self.ensure_initialized()?;
+            self.ensure_owner()?;
+            self.ensure_no_timelock()?;
+            if holders.len() > MAX_BATCH {
+                return Err(Error::BatchTooLarge);
+            }
+            let mut balances: Vec<(AccountId, Balance)> = Vec::new();
+            for holder in holders {
+                if !balances.iter().any(|(seen, _)| *seen == holder) {
+                    balances.push((holder, self.balance_of(holder)));
+                }
+            }
+            // Holders' balances are part of `total_supply`, so this cannot overflow.
+            let basis: Balance = balances.iter().map(|(_, balance)| balance).sum();
+            if basis == 0 {
+                return Err(Error::BalanceTooLow);
+            }
+            let delta = i128::try_from(total_new).map_err(|_| Error::Overflow)?;
+            self.ensure_supply_change_allowed(delta)?;
+            let mut amounts: Vec<(AccountId, Balance)> = balances
+                .iter()
+                .map(|&(holder, balance)| (holder, mul_div(total_new, balance, basis)))
+                .collect();
+            let distributed: Balance = amounts.iter().map(|(_, amount)| amount).sum();
+            let mut largest = 0;
+            for (index, (_, balance)) in balances.iter().enumerate() {
+                if *balance > balances[largest].1 {
+                    largest = index;
+                }
+            }
+            amounts[largest].1 += total_new - distributed;
+            for (holder, amount) in amounts {
+                if amount > 0 {
+                    self.mint_to(holder, amount)?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Lets `who` later claim `amount` newly minted tokens via
+        /// `claim_mint`, replacing any pending authorization.
+        #[ink(message)]
+        pub fn authorize_mint(&mut self, who: AccountId, amount: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            self.ensure_no_timelock()?;
+            self.mint_claims.insert(who, &amount);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn claimable_mint(&self, who: AccountId) -> Balance {
+            self.mint_claims.get(who).unwrap_or_default()
+        }
+
+        /// Mints the caller's authorized amount to them and clears it.
+        #[ink(message)]
+        pub fn claim_mint(&mut self) -> Result<Balance> {
+            self.ensure_initialized()?;
+            let sender = self.env().caller();
+            let amount = self.claimable_mint(sender);
+            if amount == 0 {
+                return Err(Error::NothingToClaim);
+            }
+            self.mint_claims.remove(sender);
+            self.mint_to(sender, amount)?;
+            Ok(amount)
+        }
+
+        fn mint_to(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            // A single mint is limited to `i128::MAX`, far beyond any real supply.
+            let delta = i128::try_from(value).map_err(|_| Error::Overflow)?;
+            let total_supply = self.ensure_supply_change_allowed(delta)?;
+            let shares = self.to_shares(value);
+            let total_shares = self
+                .total_shares
+                .checked_add(shares)
+                .ok_or(Error::Overflow)?;
+            if value > 0 {
+                self.note_account(to)?;
+            }
+            let shares_to = self.shares_of(to);
+            self.set_shares(to, shares_to + shares);
+            self.total_supply = total_supply;
+            self.total_shares = total_shares;
+            self.record_supply();
+            self.emit_transfer(None, to, value);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn supply_history(&self) -> Vec<(Timestamp, Balance)> {
+            self.supply_history.get().unwrap_or_default()
+        }
+
+        /// Appends the current total supply to `supply_history`, dropping the
+        /// oldest entry once the history is full.
+        fn record_supply(&mut self) {
+            let mut history = self.supply_history();
+            if history.len() >= MAX_SUPPLY_HISTORY {
+                history.remove(0);
+            }
+            history.push((self.env().block_timestamp(), self.total_supply));
+            self.supply_history.set(&history);
+        }
+
+        /// The single place where supply policy is enforced. Returns the total
+        /// supply after applying `delta` if every constraint allows it.
+        fn ensure_supply_change_allowed(&self, delta: i128) -> Result<Balance> {
+            if self.supply_frozen {
+                return Err(Error::SupplyFrozen);
+            }
+            let magnitude = delta.unsigned_abs();
+            let total_supply = if delta >= 0 {
+                self.total_supply.checked_add(magnitude)
+            } else {
+                self.total_supply.checked_sub(magnitude)
+            }
+            .ok_or(Error::Overflow)?;
+            if self.max_supply.is_some_and(|max| total_supply > max) {
+                return Err(Error::AboveMaxSupply);
+            }
+            if self.min_supply.is_some_and(|min| total_supply < min) {
+                return Err(Error::BelowMinSupply);
+            }
+            Ok(total_supply)
+        }
+
+        /// Floor `total_supply` may not be burned below, zero if unset.
+        #[ink(message)]
+        pub fn min_supply(&self) -> Balance {
+            self.min_supply.unwrap_or_default()
+        }
+
+        /// Burns `value` tokens from the caller's balance.
+        #[ink(message)]
+        pub fn burn(&mut self, value: Balance) -> Result<()> {
+            self.ensure_initialized()?;
+            let delta = i128::try_from(value).map_err(|_| Error::Overflow)?;
+            self.ensure_supply_change_allowed(-delta)?;
+            let sender = self.env().caller();
+            self.check_spendable(&sender, value)?;
+            let shares_from = self.shares_of(sender);
+            let balance = self.to_amount(shares_from);
+            let shares = if value == balance {
+                shares_from
+            } else {
+                self.to_shares(value).min(shares_from)
+            };
+            self.set_shares(sender, shares_from - shares);
+            self.total_shares -= shares;
+            self.total_supply -= value;
+            self.record_supply();
+            self.emit_transfer(Some(sender), zero_account(), value);
+            Ok(())
+        }
+
+        /// Burns like `burn` and records a categorical reason code for audits.
+        /// Codes above `MAX_BURN_REASON` are rejected.
+        #[ink(message)]
+        pub fn burn_with_reason(&mut self, value: Balance, reason: u8) -> Result<()> {
+            if reason > MAX_BURN_REASON {
+                return Err(Error::InvalidReason);
+            }
+            self.burn(value)?;
+            self.env().emit_event(Burn {
+                from: self.env().caller(),
+                value,
+                reason,
+            });
+            Ok(())
+        }
+
+        /// Sets the guardians who can jointly move the caller's balance to a new
+        /// account if its key is lost. `threshold` guardians must approve.
+        #[ink(message)]
+        pub fn set_guardians(&mut self, guardians: Vec<AccountId>, threshold: u8) -> Result<()> {
+            self.ensure_initialized()?;
+            if guardians.len() > MAX_BATCH {
+                return Err(Error::BatchTooLarge);
+            }
+            if threshold == 0 || usize::from(threshold) > guardians.len() {
+                return Err(Error::InvalidThreshold);
+            }
+            let sender = self.env().caller();
+            self.guardians.insert(sender, &(guardians, threshold));
+            Ok(())
+        }
+
+        /// Records the calling guardian's approval to move `old`'s balance to `new`.
+        #[ink(message)]
+        pub fn approve_recovery(&mut self, old: AccountId, new: AccountId) -> Result<()> {
+            self.ensure_initialized()?;
+            let sender = self.env().caller();
+            let (guardians, _) = self.guardians.get(old).unwrap_or_default();
+            if !guardians.contains(&sender) {
+                return Err(Error::NotGuardian);
+            }
+            let mut approvals = self.recovery_approvals.get((old, new)).unwrap_or_default();
+            if !approvals.contains(&sender) {
+                approvals.push(sender);
+                self.recovery_approvals.insert((old, new), &approvals);
+            }
+            Ok(())
+        }
+
+        /// Moves the whole balance of `old` to `new` once enough of `old`'s
+        /// current guardians have called `approve_recovery`. Any staked amount
+        /// stays staked under `new`, since `old` can no longer unstake it.
+        #[ink(message)]
+        pub fn recover_account(&mut self, old: AccountId, new: AccountId) -> Result<()> {
+            self.ensure_initialized()?;
+            let (guardians, threshold) = self.guardians.get(old).unwrap_or_default();
+            let approvals = self.recovery_approvals.get((old, new)).unwrap_or_default();
+            let approved = approvals
+                .iter()
+                .filter(|guardian| guardians.contains(guardian))
+                .count();
+            if threshold == 0 || approved < usize::from(threshold) {
+                return Err(Error::RecoveryThresholdNotMet);
+            }
+            self.recovery_approvals.remove((old, new));
+            let staked = self.staked_of(old);
+            self.staked.remove(old);
+            let balance = self.balance_of(old);
+            self.transfer_from_to(&old, &new, balance)?;
+            if staked > 0 {
+                let staked = (self.staked_of(new) + staked).min(self.balance_of(new));
+                self.staked.insert(new, &staked);
+            }
+            Ok(())
+        }
+
+        /// Grows or shrinks the total supply by `supply_delta`, scaling every
+        /// holder's balance proportionally while their shares stay fixed.
+        #[ink(message)]
+        pub fn rebase(&mut self, supply_delta: i128) -> Result<()> {
+            self.ensure_owner()?;
+            self.ensure_no_timelock()?;
+            self.apply_rebase(supply_delta)
+        }
+
+        fn apply_rebase(&mut self, supply_delta: i128) -> Result<()> {
+            let total_supply = self.ensure_supply_change_allowed(supply_delta)?;
+            // Once the supply reaches zero the share price is lost for good.
+            if total_supply == 0 || self.total_shares == 0 {
+                return Err(Error::InvalidRebase);
+            }
+            self.total_supply = total_supply;
+            self.record_supply();
+            self.env().emit_event(Rebase {
+                supply_delta,
+                total_supply,
+            });
+            Ok(())
+        }
+
+        /// Permanently disables mint and burn. There is no way to undo this.
+        #[ink(message)]
+        pub fn freeze_supply(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            self.supply_frozen = true;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn supply_frozen(&self) -> bool {
+            self.supply_frozen
+        }
+
+        /// Returns the sequence number of the last emitted `Transfer`.
+        #[ink(message)]
+        pub fn transfer_seq(&self) -> u64 {
+            self.transfer_seq
+        }
+
+        /// Creates an allowance pool funded from the caller's tokens that any of
+        /// `spenders` may draw from, and returns its id.
+        #[ink(message)]
+        pub fn create_pool(&mut self, spenders: Vec<AccountId>, budget: Balance) -> Result<u64> {
+            self.ensure_initialized()?;
+            if spenders.len() > MAX_BATCH {
+                return Err(Error::BatchTooLarge);
+            }
+            let sender = self.env().caller();
+            let pool_id = self.next_pool_id;
+            self.next_pool_id += 1;
+            self.pools.insert(pool_id, &(sender, budget));
+            for spender in spenders {
+                self.pool_members.insert((pool_id, spender), &true);
+            }
+            Ok(pool_id)
+        }
+
+        #[ink(message)]
+        pub fn transfer_from_pool(
+            &mut self,
+            pool_id: u64,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            self.ensure_initialized()?;
+            if self.delegated_paused {
+                return Err(Error::DelegatedPaused);
+            }
+            let sender = self.env().caller();
+            let (owner, budget) = self.pools.get(pool_id).ok_or(Error::NotPoolMember)?;
+            if owner != from || !self.pool_members.contains((pool_id, sender)) {
+                return Err(Error::NotPoolMember);
+            }
+            if budget < value {
+                return Err(Error::PoolExhausted);
+            }
+            // Self-transfers and dropped dust move nothing, so they cost the
+            // pool nothing either.
+            if from != to && !self.is_dust(value) {
+                self.pools.insert(pool_id, &(owner, budget - value));
+            }
+            self.transfer_from_to(&from, &to, value)
+        }
+
+        #[ink(message)]
+        pub fn pool_budget(&self, pool_id: u64) -> Balance {
+            self.pools
+                .get(pool_id)
+                .map(|(_, budget)| budget)
+                .unwrap_or_default()
+        }
+
+        /// Sets the transfer fee in basis points and the account receiving it.
+        #[ink(message)]
+        pub fn set_fee(&mut self, fee_bps: u16, treasury: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.ensure_no_timelock()?;
+            self.apply_fee(fee_bps, treasury)
+        }
+
+        fn apply_fee(&mut self, fee_bps: u16, treasury: AccountId) -> Result<()> {
+            if fee_bps > MAX_BPS {
+                return Err(Error::InvalidFee);
+            }
+            self.fee_bps = fee_bps;
+            self.treasury = Some(treasury);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_round_up_fees(&mut self, round_up_fees: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.ensure_no_timelock()?;
+            self.round_up_fees = round_up_fees;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn config(&self) -> Config {
+            Config {
+                owner: self.owner,
+                supply_frozen: self.supply_frozen,
+                fee_bps: self.fee_bps,
+                treasury: self.treasury,
+                round_up_fees: self.round_up_fees,
+                allow_zero_transfers: self.allow_zero_transfers,
+                max_spenders: self.max_spenders,
+                delegated_paused: self.delegated_paused,
+                outflow_limit: self.outflow_limit,
+                validator: self.validator,
+                max_known_accounts: self.max_known_accounts,
+                ticket_ttl: self.ticket_ttl,
+                quadratic_voting: self.quadratic_voting,
+                paused: self.paused,
+                breaker_threshold: self.breaker_threshold,
+                oracle: self.oracle,
+                min_price: self.min_price,
+                trading_enabled_at: self.trading_enabled_at,
+                rich_events: self.rich_events,
+                min_delay: self.min_delay,
+                distribution_phase: self.distribution_phase,
+                min_supply: self.min_supply,
+                max_supply: self.max_supply,
+                router: self.router,
+                verify_spenders: self.verify_spenders,
+                chain_id: self.chain_id,
+                seed_new_holders: self.seed_new_holders,
+                seed_amount: self.seed_amount,
+                restrict_unset_jurisdictions: self.restrict_unset_jurisdictions,
+                drop_dust: self.drop_dust,
+                dust_threshold: self.dust_threshold,
+            }
+        }
+
+        #[ink(message)]
+        pub fn nonce(&self, owner: AccountId) -> u64 {
+            self.nonces.get(owner).unwrap_or_default()
+        }
+
+        /// Returns the domain every permit signature commits to, so that it
+        /// cannot be replayed against another token, version or chain.
+        #[ink(message)]
+        pub fn domain_separator(&self) -> [u8; 32] {
+            self.env().hash_encoded::<Blake2x256, _>(&(
+                &self.name,
+                PERMIT_VERSION,
+                self.chain_id,
+                self.env().account_id(),
+            ))
+        }
+
+        #[ink(message)]
+        pub fn set_chain_id(&mut self, chain_id: u32) -> Result<()> {
+            self.ensure_owner()?;
+            self.chain_id = chain_id;
+            Ok(())
+        }
+
+        /// Returns the hash `owner` must sign with ECDSA to approve `spender`.
+        #[ink(message)]
+        pub fn permit_hash(
+            &self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            nonce: u64,
+            deadline: Timestamp,
+        ) -> [u8; 32] {
+            self.env().hash_encoded::<Blake2x256, _>(&(
+                self.domain_separator(),
+                owner,
+                spender,
+                value,
+                nonce,
+                deadline,
+            ))
+        }
+
+        /// Sets an allowance from a signature by `owner` over `permit_hash`,
+        /// so that a relayer can pay for the approval.
+        #[ink(message)]
+        pub fn permit(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            deadline: Timestamp,
+            signature: [u8; 65],
+        ) -> Result<()> {
+            self.ensure_initialized()?;
+            if self.env().block_timestamp() > deadline {
+                return Err(Error::Expired);
+            }
+            let nonce = self.nonce(owner);
+            let hash = self.permit_hash(owner, spender, value, nonce, deadline);
+            if self.recover_signer(&hash, &signature)? != owner {
+                return Err(Error::InvalidSignature);
+            }
+            self.nonces.insert(owner, &(nonce + 1));
+            self.approve_from(owner, spender, value)
+        }
+
+        /// Applies a permit and spends it in the same call. The caller must be
+        /// the permitted `spender`.
+        #[ink(message)]
+        pub fn permit_transfer_from(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            to: AccountId,
+            value: Balance,
+            deadline: Timestamp,
+            signature: [u8; 65],
+        ) -> Result<()> {
+            if spender != self.env().caller() {
+                return Err(Error::NotSpender);
+            }
+            self.permit(owner, spender, value, deadline, signature)?;
+            self.transfer_from(owner, to, value)
+        }
+
+        /// Returns the hash `owner` must sign with ECDSA to revoke `spender`.
+        /// It is tagged so that it can never collide with a `permit_hash`.
+        #[ink(message)]
+        pub fn revoke_permit_hash(
+            &self,
+            owner: AccountId,
+            spender: AccountId,
+            nonce: u64,
+            deadline: Timestamp,
+        ) -> [u8; 32] {
+            self.env().hash_encoded::<Blake2x256, _>(&(
+                b"revoke",
+                self.domain_separator(),
+                owner,
+                spender,
+                nonce,
+                deadline,
+            ))
+        }
+
+        /// Zeroes the allowance of `spender` from a signature by `owner` over
+        /// `revoke_permit_hash`, so that a relayer can pay for the revocation.
+        #[ink(message)]
+        pub fn revoke_permit(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            deadline: Timestamp,
+            signature: [u8; 65],
+        ) -> Result<()> {
+            self.ensure_initialized()?;
+            if self.env().block_timestamp() > deadline {
+                return Err(Error::Expired);
+            }
+            let nonce = self.nonce(owner);
+            let hash = self.revoke_permit_hash(owner, spender, nonce, deadline);
+            if self.recover_signer(&hash, &signature)? != owner {
+                return Err(Error::InvalidSignature);
+            }
+            self.nonces.insert(owner, &(nonce + 1));
+            self.approve_from(owner, spender, 0)
+        }
+
+        /// Recovers the account whose ECDSA key produced `signature`.
+        fn recover_signer(&self, hash: &[u8; 32], signature: &[u8; 65]) -> Result<AccountId> {
+            let pubkey = self
+                .env()
+                .ecdsa_recover(signature, hash)
+                .map_err(|_| Error::InvalidSignature)?;
+            Ok(AccountId::from(
+                self.env().hash_bytes::<Blake2x256>(&pubkey),
+            ))
+        }
+
+        /// When disabled, zero-value transfers fail with `ZeroTransfer`
+        /// instead of succeeding as a no-op.
+        #[ink(message)]
+        pub fn set_allow_zero_transfers(&mut self, allow: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.allow_zero_transfers = allow;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_max_spenders(&mut self, max_spenders: Option<u32>) -> Result<()> {
+            self.ensure_owner()?;
+            self.max_spenders = max_spenders;
+            Ok(())
+        }
+
+        /// Pauses or resumes delegated transfers (`transfer_from` and pool
+        /// spends) without affecting direct transfers.
+        #[ink(message)]
+        pub fn set_delegated_paused(&mut self, paused: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.delegated_paused = paused;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_bridge_account(&mut self, who: AccountId, is_bridge: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.bridge_accounts.insert(who, &is_bridge);
+            Ok(())
+        }
+
+        /// Limits outflow from bridge accounts to `limit_bps` of the total
+        /// supply per `window`. `None` removes the limit.
+        #[ink(message)]
+        pub fn set_outflow_limit(&mut self, outflow_limit: Option<(u16, Timestamp)>) -> Result<()> {
+            self.ensure_owner()?;
+            if outflow_limit.is_some_and(|(limit_bps, _)| limit_bps > MAX_BPS) {
+                return Err(Error::InvalidBps);
+            }
+            self.outflow_limit = outflow_limit;
+            Ok(())
+        }
+
+        /// Registers a contract exposing `validate(from, to, value) -> bool`
+        /// that is consulted on every transfer. `None` disables validation.
+        #[ink(message)]
+        pub fn set_validator(&mut self, validator: Option<AccountId>) -> Result<()> {
+            self.ensure_owner()?;
+            self.validator = validator;
+            Ok(())
+        }
+
+        /// Caps `known_accounts`, clamped to `MAX_KNOWN_ACCOUNTS`.
+        #[ink(message)]
+        pub fn set_max_known_accounts(&mut self, max_known_accounts: u32) -> Result<()> {
+            self.ensure_owner()?;
+            self.max_known_accounts = max_known_accounts.min(MAX_KNOWN_ACCOUNTS);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_ticket_ttl(&mut self, ticket_ttl: BlockNumber) -> Result<()> {
+            self.ensure_owner()?;
+            self.ticket_ttl = ticket_ttl;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_quadratic_voting(&mut self, quadratic_voting: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.quadratic_voting = quadratic_voting;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn pause(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            self.ensure_no_timelock()?;
+            self.paused = true;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn unpause(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            self.paused = false;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn paused(&self) -> bool {
+            self.paused
+        }
+
+        /// Any single `transfer`, `transfer_from` or `try_transfer` above
+        /// `breaker_threshold` pauses the contract until the owner calls
+        /// `unpause`; other transfers above it are rejected. `None` disables
+        /// the breaker.
+        #[ink(message)]
+        pub fn set_breaker_threshold(&mut self, breaker_threshold: Option<Balance>) -> Result<()> {
+            self.ensure_owner()?;
+            self.breaker_threshold = breaker_threshold;
+            Ok(())
+        }
+
+        /// Registers a contract exposing `price() -> u128`; transfers halt while
+        /// it reports less than `min_price`. `None` disables the check.
+        #[ink(message)]
+        pub fn set_oracle(&mut self, oracle: Option<AccountId>, min_price: u128) -> Result<()> {
+            self.ensure_owner()?;
+            self.oracle = oracle;
+            self.min_price = min_price;
+            Ok(())
+        }
+
+        /// Updates name and symbol. Decimals are fixed at construction and
+        /// cannot be changed here or anywhere else.
+        #[ink(message)]
+        pub fn set_metadata(&mut self, name: Option<String>, symbol: Option<String>) -> Result<()> {
+            self.ensure_owner()?;
+            self.name = name;
+            self.symbol = symbol;
+            Ok(())
+        }
+
+        /// Opens transfers to everyone at `trading_enabled_at`. Until then
+        /// only the owner and whitelisted accounts can send tokens.
+        #[ink(message)]
+        pub fn enable_trading_at(&mut self, trading_enabled_at: Timestamp) -> Result<()> {
+            self.ensure_owner()?;
+            self.trading_enabled_at = trading_enabled_at;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_trading_whitelisted(&mut self, who: AccountId, whitelisted: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.trading_whitelist.insert(who, &whitelisted);
+            Ok(())
+        }
+
+        /// Assigns `who` a jurisdiction code, or clears it with `None`.
+        #[ink(message)]
+        pub fn set_jurisdiction(&mut self, who: AccountId, code: Option<u16>) -> Result<()> {
+            self.ensure_owner()?;
+            if let Some(code) = code {
+                self.jurisdictions.insert(who, &code);
+            } else {
+                self.jurisdictions.remove(who);
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn jurisdiction_of(&self, who: AccountId) -> Option<u16> {
+            self.jurisdictions.get(who)
+        }
+
+        /// Forbids or allows transfers from jurisdiction `from` to `to`. The
+        /// rule is directional; forbid both ways to block a pair entirely.
+        #[ink(message)]
+        pub fn set_jurisdiction_forbidden(
+            &mut self,
+            from: u16,
+            to: u16,
+            forbidden: bool,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            if forbidden {
+                self.forbidden_jurisdictions.insert((from, to), &true);
+            } else {
+                self.forbidden_jurisdictions.remove((from, to));
+            }
+            Ok(())
+        }
+
+        /// When enabled, accounts without a jurisdiction can neither send nor
+        /// receive tokens.
+        #[ink(message)]
+        pub fn set_restrict_unset_jurisdictions(&mut self, restrict: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.restrict_unset_jurisdictions = restrict;
+            Ok(())
+        }
+
+        /// Makes transfers below `dust_threshold` succeed as no-ops: nothing
+        /// moves and no event is emitted. This departs from the usual token
+        /// semantics, where every successful transfer is observable, so it is
+        /// off by default and should be advertised to integrators.
+        #[ink(message)]
+        pub fn set_drop_dust(&mut self, drop_dust: bool, dust_threshold: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            self.drop_dust = drop_dust;
+            self.dust_threshold = dust_threshold;
+            Ok(())
+        }
+
+        fn is_dust(&self, value: Balance) -> bool {
+            self.drop_dust && value < self.dust_threshold
+        }
+
+        /// Enables `TransferWithBalances` events alongside `Transfer`. This
+        /// makes every transfer costlier, so it is off by default.
+        #[ink(message)]
+        pub fn set_rich_events(&mut self, rich_events: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.rich_events = rich_events;
+            Ok(())
+        }
+
+        /// Raises the timelock delay. Lowering it is itself a timelocked
+        /// action and must be queued as `AdminAction::SetMinDelay`.
+        #[ink(message)]
+        pub fn set_min_delay(&mut self, min_delay: Timestamp) -> Result<()> {
+            self.ensure_owner()?;
+            if min_delay < self.min_delay {
+                return Err(Error::TimelockNotElapsed);
+            }
+            self.min_delay = min_delay;
+            Ok(())
+        }
+
+        /// Schedules `action` to become executable after `min_delay`.
+        #[ink(message)]
+        pub fn queue_action(&mut self, action: AdminAction) -> Result<u64> {
+            self.ensure_owner()?;
+            let id = self.next_action_id;
+            self.next_action_id += 1;
+            let eta = self.env().block_timestamp().saturating_add(self.min_delay);
+            self.queued_actions.insert(id, &(action, eta));
+            Ok(id)
+        }
+
+        #[ink(message)]
+        pub fn queued_action(&self, id: u64) -> Option<(AdminAction, Timestamp)> {
+            self.queued_actions.get(id)
+        }
+
+        /// Runs a queued action once its delay has elapsed.
+        #[ink(message)]
+        pub fn execute_action(&mut self, id: u64) -> Result<()> {
+            self.ensure_owner()?;
+            let (action, eta) = self.queued_actions.get(id).ok_or(Error::ActionNotFound)?;
+            if self.env().block_timestamp() < eta {
+                return Err(Error::TimelockNotElapsed);
+            }
+            self.queued_actions.remove(id);
+            match action {
+                AdminAction::Mint(to, value) => self.mint_to(to, value),
+                AdminAction::SetFee(fee_bps, treasury) => self.apply_fee(fee_bps, treasury),
+                AdminAction::Pause => {
+                    self.paused = true;
+                    Ok(())
+                }
+                AdminAction::SetMinDelay(min_delay) => {
+                    self.min_delay = min_delay;
+                    Ok(())
+                }
+                AdminAction::AuthorizeMint(who, amount) => {
+                    self.mint_claims.insert(who, &amount);
+                    Ok(())
+                }
+                AdminAction::Rebase(supply_delta) => self.apply_rebase(supply_delta),
+                AdminAction::SetRoundUpFees(round_up_fees) => {
+                    self.round_up_fees = round_up_fees;
+                    Ok(())
+                }
+            }
+        }
+
+        /// Starts or ends the restricted distribution phase.
+        #[ink(message)]
+        pub fn set_distribution_phase(&mut self, distribution_phase: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.distribution_phase = distribution_phase;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_distribution_recipient(&mut self, who: AccountId, allowed: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.distribution_recipients.insert(who, &allowed);
+            Ok(())
+        }
+
+        /// Bounds the total supply for mint, burn and rebase.
+        #[ink(message)]
+        pub fn set_supply_bounds(
+            &mut self,
+            min_supply: Option<Balance>,
+            max_supply: Option<Balance>,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            self.min_supply = min_supply;
+            self.max_supply = max_supply;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_router(&mut self, router: Option<AccountId>) -> Result<()> {
+            self.ensure_owner()?;
+            self.router = router;
+            Ok(())
+        }
+
+        /// Configures native deposits for new holders. Any value attached is
+        /// kept by the contract to fund them.
+        #[ink(message, payable)]
+        pub fn set_seed_new_holders(&mut self, enabled: bool, seed_amount: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            self.seed_new_holders = enabled;
+            self.seed_amount = seed_amount;
+            Ok(())
+        }
+
+        /// When enabled, `approve` asks contract spenders to accept the
+        /// allowance first, at the cost of a cross-contract call.
+        #[ink(message)]
+        pub fn set_verify_spenders(&mut self, verify_spenders: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.verify_spenders = verify_spenders;
+            Ok(())
+        }
+
+        /// Freezes a further `amount` of `who`'s tokens, which then cannot be
+        /// transferred until unfrozen.
+        #[ink(message)]
+        pub fn freeze_amount(&mut self, who: AccountId, amount: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            let frozen = self
+                .frozen_of(who)
+                .checked_add(amount)
+                .ok_or(Error::Overflow)?;
+            self.frozen.insert(who, &frozen);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn unfreeze_amount(&mut self, who: AccountId, amount: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            let frozen = self.frozen_of(who).saturating_sub(amount);
+            if frozen == 0 {
+                self.frozen.remove(who);
+            } else {
+                self.frozen.insert(who, &frozen);
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn frozen_of(&self, who: AccountId) -> Balance {
+            self.frozen.get(who).unwrap_or_default()
+        }
+
+        /// Marks `who` as a system account, such as a bridge or the treasury,
+        /// whose transfers skip pauses, the circuit breaker and policy limits.
+        /// Balance and freeze checks still apply.
+        #[ink(message)]
+        pub fn set_exempt(&mut self, who: AccountId, exempt: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.exempt.insert(who, &exempt);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn is_exempt(&self, who: AccountId) -> bool {
+            self.exempt.get(who).unwrap_or_default()
+        }
+
+        /// Returns `(logic_version, storage_version)`. A storage version below
+        /// the one the running code writes means a migration is due.
+        #[ink(message)]
+        pub fn migration_info(&self) -> (u32, u32) {
+            (LOGIC_VERSION, self.storage_version)
+        }
+
+        fn ensure_owner(&self) -> Result<()> {
+            if !self.is_owner(self.env().caller()) {
+                return Err(Error::NotOwner);
+            }
+            Ok(())
+        }
+
+        /// Rejects direct calls to timelocked operations while `min_delay` is set.
+        fn ensure_no_timelock(&self) -> Result<()> {
+            if self.min_delay > 0 {
+                return Err(Error::TimelockNotElapsed);
+            }
+            Ok(())
+        }
+
+        /// Rejects calls on a `new_uninitialized` contract until `initialize`.
+        fn ensure_initialized(&self) -> Result<()> {
+            if !self.initialized {
+                return Err(Error::NotInitialized);
+            }
+            Ok(())
+        }
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
         use super::*;
         type Event = <Erc20 as ::ink::reflect::ContractEventBase>::Type;
         use ink::env::{test, DefaultEnvironment};
+        use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+        /// Deterministic xorshift generator so property tests are reproducible
+        /// without pulling in a fuzzing crate.
+        struct Rng(u64);
+
+        impl Rng {
+            fn next(&mut self) -> u64 {
+                let mut x = self.0;
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                self.0 = x;
+                x
+            }
+
+            fn below(&mut self, n: u64) -> u64 {
+                self.next() % n
+            }
+        }
+
+        /// Returns an ECDSA key and the account id derived from its public key.
+        fn ecdsa_account(seed: u8) -> (SecretKey, AccountId) {
+            let secret = SecretKey::from_slice(&[seed; 32]).expect("valid secret key");
+            let public = PublicKey::from_secret_key(&Secp256k1::new(), &secret);
+            let mut account = [0u8; 32];
+            ink::env::hash_bytes::<Blake2x256>(&public.serialize(), &mut account);
+            (secret, AccountId::from(account))
+        }
+
+        fn ecdsa_sign(secret: &SecretKey, hash: [u8; 32]) -> [u8; 65] {
+            let message = Message::from_slice(&hash).expect("32 byte hash");
+            let (recovery_id, compact) = Secp256k1::new()
+                .sign_ecdsa_recoverable(&message, secret)
+                .serialize_compact();
+            let mut signature = [0u8; 65];
+            signature[..64].copy_from_slice(&compact);
+            signature[64] = recovery_id.to_i32() as u8;
+            signature
+        }
+
+        #[ink::test]
+        fn constructor_works() {
+            let total_supply = 10_000;
+            let erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(erc20.total_supply, total_supply);
+            assert_eq!(erc20.balance_of(accounts.alice), total_supply);
+
+            let emitted_events = test::recorded_events().collect::<Vec<_>>();
+            let event = &emitted_events[0];
+            let decoded =
+                <Event as scale::Decode>::decode(&mut &event.data[..]).expect("decoded error");
+            match decoded {
+                Event::Transfer(Transfer {
+                    from, to, value, ..
+                }) => {
+                    assert!(from.is_none());
+                    assert_eq!(to, accounts.alice);
+                    assert_eq!(value, total_supply);
+                }
+                _ => panic!("Event do not match"),
+            }
+        }
+
+        #[ink::test]
+        fn transfer_should_work() {
+            let total_supply = 10_000;
+            let transfer_amount = 1_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let res = erc20.transfer(accounts.bob, transfer_amount);
+
+            assert!(res.is_ok());
+            assert_eq!(
+                erc20.balance_of(accounts.alice),
+                total_supply - transfer_amount
+            );
+            assert_eq!(erc20.balance_of(accounts.bob), transfer_amount);
+        }
+
+        #[ink::test]
+        fn invalid_transfer_should_fail() {
+            let total_supply = 10_000;
+            let transfer_amount = 1_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let res = erc20.transfer(accounts.bob, transfer_amount);
+            assert_eq!(res, Err(Error::BalanceTooLow));
+        }
+
+        #[ink::test]
+        fn approve_then_transfer_should_work() {
+            let total_supply = 10_000;
+            let approve_amount = 1_000;
+            let transfer_amount = 1_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let res = erc20.approve(accounts.bob, approve_amount);
+            assert!(res.is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let res = erc20.transfer_from(accounts.alice, accounts.charlie, transfer_amount);
+            assert!(res.is_ok());
+            let allowance = erc20.allowance(accounts.alice, accounts.bob);
+            assert_eq!(allowance, approve_amount - transfer_amount);
+        }
+
+        #[ink::test]
+        fn transfer_from_failed_when_allowance_too_low() {
+            let total_supply = 10_000;
+            let approve_amount = 999;
+            let transfer_amount = 1_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let res = erc20.approve(accounts.bob, approve_amount);
+            assert!(res.is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let res = erc20.transfer_from(accounts.alice, accounts.charlie, transfer_amount);
+            assert_eq!(res, Err(Error::AllowanceTooLow));
+        }
+
+        #[ink::test]
+        fn increase_allowance_should_work() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve(accounts.bob, 100).is_ok());
+            assert!(erc20.increase_allowance(accounts.bob, 50).is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 150);
+        }
+
+        #[ink::test]
+        fn increase_allowance_fails_on_overflow() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve(accounts.bob, Balance::MAX - 10).is_ok());
+            assert_eq!(
+                erc20.increase_allowance(accounts.bob, 11),
+                Err(Error::Overflow)
+            );
+            assert_eq!(
+                erc20.allowance(accounts.alice, accounts.bob),
+                Balance::MAX - 10
+            );
+            assert!(erc20.increase_allowance(accounts.bob, 10).is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), Balance::MAX);
+        }
+
+        #[ink::test]
+        fn zero_transfer_from_without_allowance_should_work() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let res = erc20.transfer_from(accounts.alice, accounts.charlie, 0);
+            assert!(res.is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+            assert_eq!(erc20.balance_of(accounts.alice), total_supply);
+            assert_eq!(erc20.balance_of(accounts.charlie), 0);
+
+            let emitted_events = test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(emitted_events.len(), 2);
+        }
+
+        #[ink::test]
+        fn mint_and_burn_work_before_freeze() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.mint(accounts.bob, 500).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 500);
+            assert_eq!(erc20.total_supply(), total_supply + 500);
+            assert!(erc20.burn(1_000).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), total_supply - 1_000);
+            assert_eq!(erc20.total_supply(), total_supply - 500);
+        }
+
+        #[ink::test]
+        fn mint_and_burn_fail_after_freeze() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.freeze_supply().is_ok());
+            assert_eq!(erc20.mint(accounts.bob, 500), Err(Error::SupplyFrozen));
+            assert_eq!(erc20.burn(1_000), Err(Error::SupplyFrozen));
+            assert_eq!(erc20.total_supply(), total_supply);
+        }
+
+        #[ink::test]
+        fn freeze_supply_is_irreversible() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.freeze_supply(), Err(Error::NotOwner));
+            assert!(!erc20.supply_frozen());
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.freeze_supply().is_ok());
+            assert!(erc20.freeze_supply().is_ok());
+            assert!(erc20.supply_frozen());
+        }
+
+        #[ink::test]
+        fn transfer_events_have_consecutive_seq() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            assert!(erc20.transfer(accounts.charlie, 100).is_ok());
+            assert!(erc20.mint(accounts.bob, 100).is_ok());
+            assert!(erc20.burn(100).is_ok());
+
+            let seqs = test::recorded_events()
+                .map(|event| {
+                    match <Event as scale::Decode>::decode(&mut &event.data[..])
+                        .expect("decoded error")
+                    {
+                        Event::Transfer(Transfer { seq, .. }) => seq,
+                        _ => panic!("Event do not match"),
+                    }
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(seqs, vec![1, 2, 3, 4, 5]);
+            assert_eq!(erc20.transfer_seq(), 5);
+        }
+
+        #[ink::test]
+        fn random_operations_preserve_total_supply() {
+            let mut erc20 = Erc20::new(1_000_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let holders = [
+                accounts.alice,
+                accounts.bob,
+                accounts.charlie,
+                accounts.django,
+                accounts.eve,
+                accounts.frank,
+            ];
+            let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+            for _ in 0..500 {
+                let from = holders[rng.below(holders.len() as u64) as usize];
+                let to = holders[rng.below(holders.len() as u64) as usize];
+                // Allow one more than the balance so failing paths are exercised too.
+                let value = rng.next() as Balance % (erc20.balance_of(from) + 2);
+                match rng.below(3) {
+                    0 => {
+                        test::set_caller::<DefaultEnvironment>(from);
+                        let _ = erc20.transfer(to, value);
+                    }
+                    1 => {
+                        test::set_caller::<DefaultEnvironment>(accounts.alice);
+                        let _ = erc20.mint(to, rng.below(10_000) as Balance);
+                    }
+                    _ => {
+                        test::set_caller::<DefaultEnvironment>(from);
+                        let _ = erc20.burn(value);
+                    }
+                }
+                let sum: Balance = holders.iter().map(|who| erc20.balance_of(*who)).sum();
+                assert_eq!(sum, erc20.total_supply());
+            }
+        }
+
+        #[ink::test]
+        fn self_transfer_keeps_balance() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.alice, 1_000).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), total_supply);
+        }
+
+        #[ink::test]
+        fn self_transfer_emits_no_event_and_charges_no_fee() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_fee(1_000, accounts.eve).is_ok());
+            let events_before = test::recorded_events().count();
+            let seq_before = erc20.transfer_seq();
+            assert!(erc20.transfer(accounts.alice, 1_000).is_ok());
+            assert_eq!(test::recorded_events().count(), events_before);
+            assert_eq!(erc20.transfer_seq(), seq_before);
+            assert_eq!(erc20.balance_of(accounts.alice), 10_000);
+            assert_eq!(erc20.balance_of(accounts.eve), 0);
+            assert_eq!(erc20.transfer_counts(accounts.alice), (0, 0));
+            assert_eq!(
+                erc20.transfer(accounts.alice, 10_001),
+                Err(Error::BalanceTooLow)
+            );
+        }
+
+        #[ink::test]
+        fn fees_conserve_value_when_rounding_down() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_fee(333, accounts.eve).is_ok());
+            assert_eq!(erc20.quote_transfer(1_001), (968, 33));
+            assert!(erc20.transfer(accounts.bob, 1_001).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), total_supply - 1_001);
+            assert_eq!(erc20.balance_of(accounts.bob), 968);
+            assert_eq!(erc20.balance_of(accounts.eve), 33);
+        }
+
+        #[ink::test]
+        fn fees_conserve_value_when_rounding_up() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_fee(333, accounts.eve).is_ok());
+            assert!(erc20.set_round_up_fees(true).is_ok());
+            assert_eq!(erc20.quote_transfer(1_001), (967, 34));
+            assert!(erc20.transfer(accounts.bob, 1_001).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), total_supply - 1_001);
+            assert_eq!(erc20.balance_of(accounts.bob), 967);
+            assert_eq!(erc20.balance_of(accounts.eve), 34);
+            assert_eq!(
+                erc20.set_fee(MAX_BPS + 1, accounts.eve),
+                Err(Error::InvalidFee)
+            );
+        }
+
+        #[ink::test]
+        fn config_reflects_settings() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(
+                erc20.config(),
+                Config {
+                    owner: Some(accounts.alice),
+                    supply_frozen: false,
+                    fee_bps: 0,
+                    treasury: None,
+                    round_up_fees: false,
+                    allow_zero_transfers: true,
+                    max_spenders: None,
+                    delegated_paused: false,
+                    outflow_limit: None,
+                    validator: None,
+                    max_known_accounts: DEFAULT_MAX_KNOWN_ACCOUNTS,
+                    ticket_ttl: DEFAULT_TICKET_TTL,
+                    quadratic_voting: false,
+                    paused: false,
+                    breaker_threshold: None,
+                    oracle: None,
+                    min_price: 0,
+                    trading_enabled_at: 0,
+                    rich_events: false,
+                    min_delay: 0,
+                    distribution_phase: false,
+                    min_supply: None,
+                    max_supply: None,
+                    router: None,
+                    verify_spenders: false,
+                    chain_id: 0,
+                    seed_new_holders: false,
+                    seed_amount: 0,
+                    restrict_unset_jurisdictions: false,
+                    drop_dust: false,
+                    dust_threshold: 0,
+                }
+            );
+            assert!(erc20.set_fee(25, accounts.eve).is_ok());
+            assert!(erc20.set_round_up_fees(true).is_ok());
+            assert!(erc20.freeze_supply().is_ok());
+            let config = erc20.config();
+            assert_eq!(config.fee_bps, 25);
+            assert_eq!(config.treasury, Some(accounts.eve));
+            assert!(config.round_up_fees);
+            assert!(config.supply_frozen);
+        }
+
+        #[ink::test]
+        fn permit_transfer_from_consumes_nonce_and_moves_tokens() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let (secret, owner) = ecdsa_account(0x11);
+            assert!(erc20.mint(owner, 1_000).is_ok());
+
+            let deadline = 100;
+            let hash = erc20.permit_hash(owner, accounts.bob, 400, 0, deadline);
+            let signature = ecdsa_sign(&secret, hash);
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            let res = erc20.permit_transfer_from(
+                owner,
+                accounts.bob,
+                accounts.eve,
+                400,
+                deadline,
+                signature,
+            );
+            assert_eq!(res, Err(Error::NotSpender));
+            assert_eq!(erc20.nonce(owner), 0);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let res = erc20.permit_transfer_from(
+                owner,
+                accounts.bob,
+                accounts.charlie,
+                400,
+                deadline,
+                signature,
+            );
+            assert!(res.is_ok());
+            assert_eq!(erc20.nonce(owner), 1);
+            assert_eq!(erc20.balance_of(owner), 600);
+            assert_eq!(erc20.balance_of(accounts.charlie), 400);
+            assert_eq!(erc20.allowance(owner, accounts.bob), 0);
+            // The permit is recorded like any other approval.
+            assert_eq!(erc20.allowance_history(owner, accounts.bob), vec![(0, 400)]);
+
+            let res = erc20.permit(owner, accounts.bob, 400, deadline, signature);
+            assert_eq!(res, Err(Error::InvalidSignature));
+        }
+
+        #[ink::test]
+        fn permit_fails_after_deadline() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let (secret, owner) = ecdsa_account(0x11);
+            let hash = erc20.permit_hash(owner, accounts.bob, 400, 0, 100);
+            let signature = ecdsa_sign(&secret, hash);
+            test::set_block_timestamp::<DefaultEnvironment>(101);
+            let res = erc20.permit(owner, accounts.bob, 400, 100, signature);
+            assert_eq!(res, Err(Error::Expired));
+            assert_eq!(erc20.nonce(owner), 0);
+        }
+
+        #[ink::test]
+        fn revoke_permit_zeroes_allowance_once() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let (secret, owner) = ecdsa_account(0x11);
+            let hash = erc20.permit_hash(owner, accounts.bob, 400, 0, 100);
+            let signature = ecdsa_sign(&secret, hash);
+            assert!(erc20
+                .permit(owner, accounts.bob, 400, 100, signature)
+                .is_ok());
+            assert_eq!(erc20.allowance(owner, accounts.bob), 400);
+
+            // An approval signature cannot be replayed as a revocation.
+            assert_eq!(
+                erc20.revoke_permit(owner, accounts.bob, 100, signature),
+                Err(Error::InvalidSignature)
+            );
+
+            let hash = erc20.revoke_permit_hash(owner, accounts.bob, 1, 100);
+            let signature = ecdsa_sign(&secret, hash);
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert!(erc20
+                .revoke_permit(owner, accounts.bob, 100, signature)
+                .is_ok());
+            assert_eq!(erc20.allowance(owner, accounts.bob), 0);
+            assert_eq!(erc20.nonce(owner), 2);
+            assert_eq!(
+                erc20.revoke_permit(owner, accounts.bob, 100, signature),
+                Err(Error::InvalidSignature)
+            );
+        }
+
+        #[ink::test]
+        fn pool_spenders_share_budget_until_exhausted() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let pool_id = erc20
+                .create_pool(vec![accounts.bob, accounts.charlie], 300)
+                .unwrap();
+            assert_eq!(erc20.pool_budget(pool_id), 300);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let res = erc20.transfer_from_pool(pool_id, accounts.alice, accounts.bob, 200);
+            assert!(res.is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            let res = erc20.transfer_from_pool(pool_id, accounts.alice, accounts.charlie, 100);
+            assert!(res.is_ok());
+            assert_eq!(erc20.pool_budget(pool_id), 0);
+
+            let res = erc20.transfer_from_pool(pool_id, accounts.alice, accounts.charlie, 1);
+            assert_eq!(res, Err(Error::PoolExhausted));
+            assert_eq!(erc20.balance_of(accounts.alice), total_supply - 300);
+            assert_eq!(erc20.balance_of(accounts.bob), 200);
+            assert_eq!(erc20.balance_of(accounts.charlie), 100);
+        }
+
+        #[ink::test]
+        fn transfer_from_pool_does_not_charge_dropped_dust() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let pool_id = erc20.create_pool(vec![accounts.bob], 300).unwrap();
+            assert!(erc20.set_drop_dust(true, 10).is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let res = erc20.transfer_from_pool(pool_id, accounts.alice, accounts.bob, 9);
+            assert!(res.is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+            assert_eq!(erc20.pool_budget(pool_id), 300);
+
+            let res = erc20.transfer_from_pool(pool_id, accounts.alice, accounts.bob, 10);
+            assert!(res.is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 10);
+            assert_eq!(erc20.pool_budget(pool_id), 290);
+        }
+
+        #[ink::test]
+        fn transfer_from_pool_rejects_non_members() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let pool_id = erc20.create_pool(vec![accounts.bob], 300).unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.django);
+            let res = erc20.transfer_from_pool(pool_id, accounts.alice, accounts.django, 100);
+            assert_eq!(res, Err(Error::NotPoolMember));
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let res = erc20.transfer_from_pool(pool_id, accounts.charlie, accounts.bob, 100);
+            assert_eq!(res, Err(Error::NotPoolMember));
+            let res = erc20.transfer_from_pool(pool_id + 1, accounts.alice, accounts.bob, 100);
+            assert_eq!(res, Err(Error::NotPoolMember));
+        }
+
+        #[ink::test]
+        fn zero_transfers_allowed_by_default() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 0).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.transfer_from(accounts.alice, accounts.bob, 0).is_ok());
+        }
+
+        #[ink::test]
+        fn zero_transfers_fail_in_strict_mode() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_allow_zero_transfers(false).is_ok());
+            assert_eq!(erc20.transfer(accounts.bob, 0), Err(Error::ZeroTransfer));
+            assert!(erc20.transfer(accounts.bob, 1).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 0),
+                Err(Error::ZeroTransfer)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_and_approve_should_work() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer_and_approve(accounts.bob, 1_000, 500).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), total_supply - 1_000);
+            assert_eq!(erc20.balance_of(accounts.bob), 1_000);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 500);
+
+            let res = erc20.transfer_and_approve(accounts.charlie, total_supply, 500);
+            assert_eq!(res, Err(Error::BalanceTooLow));
+            assert_eq!(erc20.allowance(accounts.alice, accounts.charlie), 0);
+        }
+
+        #[ink::test]
+        fn drained_balance_entry_is_removed_and_reads_zero() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 1_000).is_ok());
+            assert!(erc20.balances.contains(accounts.bob));
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.transfer(accounts.charlie, 1_000).is_ok());
+            assert!(!erc20.balances.contains(accounts.bob));
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.transfer(accounts.bob, 300).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 300);
+        }
+
+        #[ink::test]
+        fn burning_entire_balance_removes_entry() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.burn(total_supply).is_ok());
+            assert!(!erc20.balances.contains(accounts.alice));
+            assert_eq!(erc20.balance_of(accounts.alice), 0);
+            assert!(erc20.mint(accounts.alice, 50).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), 50);
+        }
+
+        #[ink::test]
+        fn spender_count_tracks_non_zero_allowances() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve(accounts.bob, 100).is_ok());
+            assert_eq!(erc20.spender_count(accounts.alice), 1);
+            assert!(erc20.approve(accounts.bob, 200).is_ok());
+            assert_eq!(erc20.spender_count(accounts.alice), 1);
+            assert!(erc20.approve(accounts.charlie, 0).is_ok());
+            assert_eq!(erc20.spender_count(accounts.alice), 1);
+            assert!(erc20.approve(accounts.bob, 0).is_ok());
+            assert_eq!(erc20.spender_count(accounts.alice), 0);
+            assert!(erc20.approve(accounts.bob, 0).is_ok());
+            assert_eq!(erc20.spender_count(accounts.alice), 0);
+        }
+
+        #[ink::test]
+        fn max_spenders_caps_new_approvals() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_max_spenders(Some(2)).is_ok());
+            assert!(erc20.approve(accounts.bob, 100).is_ok());
+            assert!(erc20.approve(accounts.charlie, 100).is_ok());
+            assert_eq!(
+                erc20.approve(accounts.django, 100),
+                Err(Error::TooManySpenders)
+            );
+            assert!(erc20.approve(accounts.bob, 150).is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.bob, 150)
+                .is_ok());
+            assert_eq!(erc20.spender_count(accounts.alice), 1);
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.approve(accounts.django, 100).is_ok());
+            assert_eq!(erc20.spender_count(accounts.alice), 2);
+        }
+
+        #[ink::test]
+        fn delegated_pause_blocks_only_transfer_from() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve(accounts.bob, 1_000).is_ok());
+            assert!(erc20.set_delegated_paused(true).is_ok());
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 100),
+                Err(Error::DelegatedPaused)
+            );
+            assert_eq!(erc20.set_delegated_paused(false), Err(Error::NotOwner));
+            assert!(erc20.transfer(accounts.charlie, 100).is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.set_delegated_paused(false).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.bob, 100)
+                .is_ok());
+        }
+
+        #[ink::test]
+        fn bridge_outflow_is_capped_per_window() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 5_000).is_ok());
+            assert!(erc20.set_bridge_account(accounts.bob, true).is_ok());
+            assert!(erc20.set_outflow_limit(Some((1_000, 100))).is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.transfer(accounts.charlie, 600).is_ok());
+            assert!(erc20.transfer(accounts.charlie, 400).is_ok());
+            assert_eq!(
+                erc20.transfer(accounts.charlie, 1),
+                Err(Error::GlobalLimitExceeded)
+            );
+
+            test::set_block_timestamp::<DefaultEnvironment>(99);
+            assert_eq!(
+                erc20.transfer(accounts.charlie, 1),
+                Err(Error::GlobalLimitExceeded)
+            );
+            test::set_block_timestamp::<DefaultEnvironment>(100);
+            assert!(erc20.transfer(accounts.charlie, 1_000).is_ok());
+            assert_eq!(erc20.balance_of(accounts.charlie), 2_000);
+        }
+
+        #[ink::test]
+        fn non_bridge_accounts_are_not_capped() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_outflow_limit(Some((1_000, 100))).is_ok());
+            assert!(erc20.transfer(accounts.bob, 5_000).is_ok());
+            assert_eq!(
+                erc20.set_outflow_limit(Some((MAX_BPS + 1, 100))),
+                Err(Error::InvalidBps)
+            );
+        }
+
+        #[ink::test]
+        fn new_with_owner_assigns_owner_and_holder() {
+            let total_supply = 10_000;
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mut erc20 = Erc20::new_with_owner(total_supply, accounts.bob, accounts.charlie);
+            assert_eq!(erc20.owner(), Some(accounts.bob));
+            assert_eq!(erc20.balance_of(accounts.charlie), total_supply);
+            assert_eq!(erc20.balance_of(accounts.alice), 0);
+            assert_eq!(erc20.mint(accounts.alice, 1), Err(Error::NotOwner));
+
+            let emitted_events = test::recorded_events().collect::<Vec<_>>();
+            let decoded = <Event as scale::Decode>::decode(&mut &emitted_events[0].data[..])
+                .expect("decoded error");
+            match decoded {
+                Event::Transfer(Transfer {
+                    from, to, value, ..
+                }) => {
+                    assert!(from.is_none());
+                    assert_eq!(to, accounts.charlie);
+                    assert_eq!(value, total_supply);
+                }
+                _ => panic!("Event do not match"),
+            }
+        }
+
+        #[ink::test]
+        fn set_validator_is_owner_only() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.set_validator(Some(accounts.django)),
+                Err(Error::NotOwner)
+            );
+            assert_eq!(erc20.config().validator, None);
+            assert!(erc20.transfer(accounts.charlie, 0).is_ok());
+        }
+
+        #[ink::test]
+        fn error_as_str_works() {
+            let expected = [
+                (Error::BalanceTooLow, "balance_too_low"),
+                (Error::AllowanceTooLow, "allowance_too_low"),
+                (Error::NotOwner, "not_owner"),
+                (Error::SupplyFrozen, "supply_frozen"),
+                (Error::Overflow, "overflow"),
+                (Error::NotPoolMember, "not_pool_member"),
+                (Error::PoolExhausted, "pool_exhausted"),
+                (Error::InvalidFee, "invalid_fee"),
+                (Error::InvalidSignature, "invalid_signature"),
+                (Error::Expired, "expired"),
+                (Error::ZeroTransfer, "zero_transfer"),
+                (Error::TooManySpenders, "too_many_spenders"),
+                (Error::DelegatedPaused, "delegated_paused"),
+                (Error::InvalidBps, "invalid_bps"),
+                (Error::GlobalLimitExceeded, "global_limit_exceeded"),
+                (Error::ValidationFailed, "validation_failed"),
+                (Error::TooManyAccounts, "too_many_accounts"),
+                (Error::TicketNotFound, "ticket_not_found"),
+                (Error::TicketExpired, "ticket_expired"),
+                (Error::InvalidReason, "invalid_reason"),
+                (Error::NothingToClaim, "nothing_to_claim"),
+                (Error::DuplicateRequest, "duplicate_request"),
+                (Error::CallbackFailed, "callback_failed"),
+                (Error::Paused, "paused"),
+                (Error::InvalidThreshold, "invalid_threshold"),
+                (Error::NotGuardian, "not_guardian"),
+                (Error::RecoveryThresholdNotMet, "recovery_threshold_not_met"),
+                (Error::PriceFloorBreached, "price_floor_breached"),
+                (Error::InvalidRebase, "invalid_rebase"),
+                (Error::AboveBreakerThreshold, "above_breaker_threshold"),
+                (Error::AlreadyInitialized, "already_initialized"),
+                (Error::TradingNotStarted, "trading_not_started"),
+                (Error::TipFailed, "tip_failed"),
+                (Error::NotInitialized, "not_initialized"),
+                (Error::TimelockNotElapsed, "timelock_not_elapsed"),
+                (Error::ActionNotFound, "action_not_found"),
+                (Error::PeriodLimitExceeded, "period_limit_exceeded"),
+                (Error::SupplyMismatch, "supply_mismatch"),
+                (Error::DistributionPhaseActive, "distribution_phase_active"),
+                (Error::AboveMaxSupply, "above_max_supply"),
+                (Error::BelowMinSupply, "below_min_supply"),
+                (Error::RouterCallFailed, "router_call_failed"),
+                (Error::InvalidSchedule, "invalid_schedule"),
+                (Error::VestingExists, "vesting_exists"),
+                (Error::SpenderRejected, "spender_rejected"),
+                (Error::AlreadyGranted, "already_granted"),
+                (Error::BalanceFrozen, "balance_frozen"),
+                (Error::AlreadyMigrated, "already_migrated"),
+                (Error::BalanceStaked, "balance_staked"),
+                (Error::FeeTooHigh, "fee_too_high"),
+                (Error::NotOperator, "not_operator"),
+                (Error::BatchTooLarge, "batch_too_large"),
+                (Error::JurisdictionForbidden, "jurisdiction_forbidden"),
+                (Error::DustLeg, "dust_leg"),
+                (Error::NotSpender, "not_spender"),
+                (Error::InvalidPeriod, "invalid_period"),
+                (Error::TooManyLocks, "too_many_locks"),
+            ];
+            for (error, name) in expected {
+                assert_eq!(error.as_str(), name);
+            }
+        }
+
+        #[ink::test]
+        fn transfer_split_conserves_value() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20
+                .transfer_split(accounts.bob, accounts.charlie, 1_001, 3_333)
+                .is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 333);
+            assert_eq!(erc20.balance_of(accounts.charlie), 668);
+            assert_eq!(erc20.balance_of(accounts.alice), total_supply - 1_001);
+
+            assert!(erc20
+                .transfer_split(accounts.bob, accounts.charlie, 7, MAX_BPS)
+                .is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 340);
+            assert_eq!(erc20.balance_of(accounts.charlie), 668);
+            // One event from the constructor plus two per split.
+            assert_eq!(test::recorded_events().count(), 5);
+        }
+
+        #[ink::test]
+        fn transfer_split_rejects_invalid_bps() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(
+                erc20.transfer_split(accounts.bob, accounts.charlie, 100, MAX_BPS + 1),
+                Err(Error::InvalidBps)
+            );
+            assert_eq!(
+                erc20.transfer_split(accounts.bob, accounts.charlie, 10_001, 5_000),
+                Err(Error::BalanceTooLow)
+            );
+        }
+
+        #[ink::test]
+        fn known_accounts_are_appended_once() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(erc20.all_known_accounts(), vec![accounts.alice]);
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            assert!(erc20.mint(accounts.charlie, 100).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.transfer(accounts.alice, 200).is_ok());
+            assert_eq!(
+                erc20.all_known_accounts(),
+                vec![accounts.alice, accounts.bob, accounts.charlie]
+            );
+        }
+
+        #[ink::test]
+        fn known_accounts_cap_rejects_new_recipients() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_max_known_accounts(2).is_ok());
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            assert_eq!(
+                erc20.transfer(accounts.charlie, 100),
+                Err(Error::TooManyAccounts)
+            );
+            assert_eq!(
+                erc20.mint(accounts.charlie, 100),
+                Err(Error::TooManyAccounts)
+            );
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            assert_eq!(erc20.balance_of(accounts.charlie), 0);
+
+            assert!(erc20.set_max_known_accounts(u32::MAX).is_ok());
+            assert_eq!(erc20.config().max_known_accounts, MAX_KNOWN_ACCOUNTS);
+        }
+
+        #[ink::test]
+        fn prepare_then_execute_transfer_works() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let ticket = erc20.prepare_transfer(accounts.bob, 1_000).unwrap();
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.execute_transfer(ticket), Err(Error::TicketNotFound));
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            test::advance_block::<DefaultEnvironment>();
+            assert!(erc20.execute_transfer(ticket).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 1_000);
+            assert_eq!(erc20.balance_of(accounts.alice), total_supply - 1_000);
+            assert_eq!(erc20.execute_transfer(ticket), Err(Error::TicketNotFound));
+        }
+
+        #[ink::test]
+        fn executing_expired_ticket_fails() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let ticket = erc20.prepare_transfer(accounts.bob, 1_000).unwrap();
+            for _ in 0..=DEFAULT_TICKET_TTL {
+                test::advance_block::<DefaultEnvironment>();
+            }
+            assert_eq!(erc20.execute_transfer(ticket), Err(Error::TicketExpired));
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn burn_with_reason_emits_burn_event() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.burn_with_reason(100, 2).is_ok());
+            assert_eq!(erc20.total_supply(), total_supply - 100);
+
+            let emitted_events = test::recorded_events().collect::<Vec<_>>();
+            let decoded = <Event as scale::Decode>::decode(
+                &mut &emitted_events.last().expect("no events").data[..],
+            )
+            .expect("decoded error");
+            match decoded {
+                Event::Burn(Burn {
+                    from,
+                    value,
+                    reason,
+                }) => {
+                    assert_eq!(from, accounts.alice);
+                    assert_eq!(value, 100);
+                    assert_eq!(reason, 2);
+                }
+                _ => panic!("Event do not match"),
+            }
+        }
+
+        #[ink::test]
+        fn burn_with_out_of_range_reason_fails() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            assert_eq!(
+                erc20.burn_with_reason(100, MAX_BURN_REASON + 1),
+                Err(Error::InvalidReason)
+            );
+            assert_eq!(erc20.total_supply(), total_supply);
+        }
+
+        #[ink::test]
+        fn authorize_then_claim_mint_works() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.authorize_mint(accounts.bob, 500).is_ok());
+            assert_eq!(erc20.claimable_mint(accounts.bob), 500);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.claim_mint(), Ok(500));
+            assert_eq!(erc20.balance_of(accounts.bob), 500);
+            assert_eq!(erc20.total_supply(), total_supply + 500);
+            assert_eq!(erc20.claim_mint(), Err(Error::NothingToClaim));
+        }
+
+        #[ink::test]
+        fn unauthorized_claim_mint_fails() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.authorize_mint(accounts.bob, 500),
+                Err(Error::NotOwner)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(erc20.claim_mint(), Err(Error::NothingToClaim));
+        }
+
+        #[ink::test]
+        fn transfer_idempotent_rejects_replayed_key() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let key = [7u8; 32];
+            assert!(erc20.transfer_idempotent(accounts.bob, 100, key).is_ok());
+            assert_eq!(
+                erc20.transfer_idempotent(accounts.bob, 100, key),
+                Err(Error::DuplicateRequest)
+            );
+            assert_eq!(erc20.balance_of(accounts.bob), 100);
+
+            // Keys are scoped per caller.
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20
+                .transfer_idempotent(accounts.charlie, 100, key)
+                .is_ok());
+            assert_eq!(erc20.balance_of(accounts.charlie), 100);
+        }
+
+        #[ink::test]
+        fn voting_power_is_linear_by_default() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 1_000).is_ok());
+            assert_eq!(erc20.voting_power(accounts.alice), 9_000);
+            assert_eq!(erc20.voting_power(accounts.bob), 1_000);
+            assert_eq!(erc20.voting_power(accounts.charlie), 0);
+        }
+
+        #[ink::test]
+        fn voting_power_is_square_root_when_quadratic() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_quadratic_voting(true).is_ok());
+            assert_eq!(erc20.voting_power(accounts.alice), 100);
+            assert!(erc20.transfer(accounts.bob, 1_000).is_ok());
+            assert_eq!(erc20.voting_power(accounts.bob), 31);
+            assert_eq!(erc20.voting_power(accounts.charlie), 0);
+            assert_eq!(isqrt(Balance::MAX), u64::MAX as Balance);
+        }
+
+        #[ink::test]
+        fn silent_transfer_emits_no_event() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.silent_transfer(accounts.bob, 1_000).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), total_supply - 1_000);
+            assert_eq!(erc20.balance_of(accounts.bob), 1_000);
+            // Only the constructor's `Transfer` was recorded.
+            assert_eq!(test::recorded_events().count(), 1);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.silent_transfer(accounts.charlie, 100),
+                Err(Error::NotOwner)
+            );
+        }
+
+        #[ink::test]
+        fn infinite_allowance_spends_gross_value_on_fee_token() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_fee(100, accounts.eve).is_ok());
+            assert!(erc20.approve(accounts.bob, Balance::MAX).is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.charlie, 1_000)
+                .is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), Balance::MAX);
+            assert_eq!(erc20.balance_of(accounts.alice), total_supply - 1_000);
+            assert_eq!(erc20.balance_of(accounts.eve), 10);
+            assert_eq!(erc20.balance_of(accounts.charlie), 990);
+        }
+
+        #[ink::test]
+        fn total_accounts_ever_only_grows() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(erc20.total_accounts_ever(), 1);
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            assert_eq!(erc20.total_accounts_ever(), 2);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.transfer(accounts.alice, 100).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+            assert_eq!(erc20.total_accounts_ever(), 2);
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            assert!(erc20.transfer(accounts.charlie, 100).is_ok());
+            assert_eq!(erc20.total_accounts_ever(), 3);
+        }
+
+        #[ink::test]
+        fn pause_blocks_transfers() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve(accounts.bob, 100).is_ok());
+            assert!(erc20.pause().is_ok());
+            assert_eq!(erc20.transfer(accounts.bob, 100), Err(Error::Paused));
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 100),
+                Err(Error::Paused)
+            );
+            assert_eq!(erc20.unpause(), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn circuit_breaker_pauses_on_large_transfer() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_breaker_threshold(Some(1_000)).is_ok());
+            assert!(erc20.transfer(accounts.bob, 1_000).is_ok());
+            assert!(!erc20.paused());
+
+            assert!(erc20.transfer(accounts.bob, 1_001).is_ok());
+            assert!(erc20.paused());
+            assert_eq!(erc20.balance_of(accounts.bob), 1_000);
+            let emitted_events = test::recorded_events().collect::<Vec<_>>();
+            let decoded = <Event as scale::Decode>::decode(
+                &mut &emitted_events.last().expect("no events").data[..],
+            )
+            .expect("decoded error");
+            match decoded {
+                Event::CircuitBreakerTripped(CircuitBreakerTripped {
+                    value, threshold, ..
+                }) => {
+                    assert_eq!(value, 1_001);
+                    assert_eq!(threshold, 1_000);
+                }
+                _ => panic!("Event do not match"),
+            }
+
+            assert_eq!(erc20.transfer(accounts.bob, 10), Err(Error::Paused));
+            assert!(erc20.unpause().is_ok());
+            assert!(erc20.transfer(accounts.bob, 10).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 1_010);
+        }
+
+        #[ink::test]
+        fn circuit_breaker_only_trips_on_authorized_transfers() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_breaker_threshold(Some(1_000)).is_ok());
+
+            // Neither an unfunded sender nor a spender without allowance can
+            // trip the breaker.
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer(accounts.charlie, 2_000),
+                Err(Error::BalanceTooLow)
+            );
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 2_000),
+                Err(Error::AllowanceTooLow)
+            );
+            assert!(!erc20.paused());
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.approve(accounts.bob, 5_000).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.bob, 2_000)
+                .is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 5_000);
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+            assert!(erc20.paused());
+
+            // Paths that cannot report the trip reject the transfer instead.
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.unpause().is_ok());
+            assert_eq!(
+                erc20.transfer_split(accounts.bob, accounts.charlie, 4_000, 5_000),
+                Err(Error::AboveBreakerThreshold)
+            );
+            assert!(!erc20.paused());
+            assert_eq!(
+                erc20.try_transfer(accounts.bob, 2_000),
+                TransferOutcome::BreakerTripped
+            );
+            assert!(erc20.paused());
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn allowance_reads_zero_after_expiry() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve_with_expiry(accounts.bob, 100, 50).is_ok());
+            test::set_block_timestamp::<DefaultEnvironment>(50);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 100);
+
+            test::set_block_timestamp::<DefaultEnvironment>(51);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+            assert_eq!(
+                erc20.allowances.get((accounts.alice, accounts.bob)),
+                Some(100)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 1),
+                Err(Error::AllowanceTooLow)
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.approve(accounts.bob, 100).is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 100);
+        }
+
+        #[ink::test]
+        fn adjusting_an_expiring_allowance_keeps_its_expiry() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve_with_expiry(accounts.bob, 100, 50).is_ok());
+            assert!(erc20.increase_allowance(accounts.bob, 50).is_ok());
+            assert_eq!(erc20.decrease_allowance(accounts.bob, 30), Ok(120));
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 120);
+            test::set_block_timestamp::<DefaultEnvironment>(51);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+
+            // Once expired, an increase starts a fresh allowance.
+            assert!(erc20.increase_allowance(accounts.bob, 10).is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 10);
+
+            // A plain `approve` still clears the expiry.
+            assert!(erc20.approve_with_expiry(accounts.bob, 100, 60).is_ok());
+            assert!(erc20.approve(accounts.bob, 100).is_ok());
+            test::set_block_timestamp::<DefaultEnvironment>(61);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 100);
+        }
+
+        #[ink::test]
+        fn recover_account_fails_below_threshold() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20
+                .set_guardians(vec![accounts.bob, accounts.charlie, accounts.django], 2)
+                .is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.approve_recovery(accounts.alice, accounts.eve).is_ok());
+            assert!(erc20.approve_recovery(accounts.alice, accounts.eve).is_ok());
+            assert_eq!(
+                erc20.recover_account(accounts.alice, accounts.eve),
+                Err(Error::RecoveryThresholdNotMet)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                erc20.approve_recovery(accounts.alice, accounts.eve),
+                Err(Error::NotGuardian)
+            );
+            assert_eq!(erc20.balance_of(accounts.eve), 0);
+        }
+
+        #[ink::test]
+        fn recover_account_moves_balance_at_threshold() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(
+                erc20.set_guardians(vec![accounts.bob], 2),
+                Err(Error::InvalidThreshold)
+            );
+            assert!(erc20
+                .set_guardians(vec![accounts.bob, accounts.charlie, accounts.django], 2)
+                .is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.approve_recovery(accounts.alice, accounts.eve).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.django);
+            assert!(erc20.approve_recovery(accounts.alice, accounts.eve).is_ok());
+            assert!(erc20.recover_account(accounts.alice, accounts.eve).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), 0);
+            assert_eq!(erc20.balance_of(accounts.eve), total_supply);
+        }
+
+        #[ink::test]
+        fn recover_account_carries_staked_balance() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.stake(4_000).is_ok());
+            assert!(erc20
+                .set_guardians(vec![accounts.bob, accounts.charlie], 2)
+                .is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.approve_recovery(accounts.alice, accounts.eve).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert!(erc20.approve_recovery(accounts.alice, accounts.eve).is_ok());
+            assert!(erc20.recover_account(accounts.alice, accounts.eve).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), 0);
+            assert_eq!(erc20.staked_of(accounts.alice), 0);
+            assert_eq!(erc20.balance_of(accounts.eve), 10_000);
+            assert_eq!(erc20.staked_of(accounts.eve), 4_000);
+
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                erc20.transfer(accounts.bob, 7_000),
+                Err(Error::BalanceStaked)
+            );
+            assert!(erc20.unstake(4_000).is_ok());
+            assert!(erc20.transfer(accounts.bob, 7_000).is_ok());
+        }
+
+        #[ink::test]
+        fn error_discriminants_are_stable() {
+            let expected = [
+                (Error::BalanceTooLow, 0),
+                (Error::AllowanceTooLow, 1),
+                (Error::NotOwner, 2),
+                (Error::SupplyFrozen, 3),
+                (Error::Overflow, 4),
+                (Error::NotPoolMember, 5),
+                (Error::PoolExhausted, 6),
+                (Error::InvalidFee, 7),
+                (Error::InvalidSignature, 8),
+                (Error::Expired, 9),
+                (Error::ZeroTransfer, 10),
+                (Error::TooManySpenders, 11),
+                (Error::DelegatedPaused, 12),
+                (Error::InvalidBps, 13),
+                (Error::GlobalLimitExceeded, 14),
+                (Error::ValidationFailed, 15),
+                (Error::TooManyAccounts, 16),
+                (Error::TicketNotFound, 17),
+                (Error::TicketExpired, 18),
+                (Error::InvalidReason, 19),
+                (Error::NothingToClaim, 20),
+                (Error::DuplicateRequest, 21),
+                (Error::CallbackFailed, 22),
+                (Error::Paused, 23),
+                (Error::InvalidThreshold, 24),
+                (Error::NotGuardian, 25),
+                (Error::RecoveryThresholdNotMet, 26),
+                (Error::PriceFloorBreached, 27),
+                (Error::InvalidRebase, 28),
+                (Error::AboveBreakerThreshold, 29),
+                (Error::AlreadyInitialized, 30),
+                (Error::TradingNotStarted, 31),
+                (Error::TipFailed, 32),
+                (Error::NotInitialized, 33),
+                (Error::TimelockNotElapsed, 34),
+                (Error::ActionNotFound, 35),
+                (Error::PeriodLimitExceeded, 36),
+                (Error::SupplyMismatch, 37),
+                (Error::DistributionPhaseActive, 38),
+                (Error::AboveMaxSupply, 39),
+                (Error::BelowMinSupply, 40),
+                (Error::RouterCallFailed, 41),
+                (Error::InvalidSchedule, 42),
+                (Error::VestingExists, 43),
+                (Error::SpenderRejected, 44),
+                (Error::AlreadyGranted, 45),
+                (Error::BalanceFrozen, 46),
+                (Error::AlreadyMigrated, 47),
+                (Error::BalanceStaked, 48),
+                (Error::FeeTooHigh, 49),
+                (Error::NotOperator, 50),
+                (Error::BatchTooLarge, 51),
+                (Error::JurisdictionForbidden, 52),
+                (Error::DustLeg, 53),
+                (Error::NotSpender, 54),
+                (Error::InvalidPeriod, 55),
+                (Error::TooManyLocks, 56),
+            ];
+            for (error, discriminant) in expected {
+                assert_eq!(scale::Encode::encode(&error), vec![discriminant]);
+                let decoded = <Error as scale::Decode>::decode(&mut &[discriminant][..])
+                    .expect("decoded error");
+                assert_eq!(decoded, error);
+            }
+            let next = expected.len() as u8;
+            assert!(<Error as scale::Decode>::decode(&mut &[next][..]).is_err());
+        }
+
+        #[ink::test]
+        fn set_oracle_is_owner_only() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.set_oracle(Some(accounts.django), 100),
+                Err(Error::NotOwner)
+            );
+            assert_eq!(erc20.config().oracle, None);
+        }
+
+        #[ink::test]
+        fn decrease_allowance_to_zero_removes_entry() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve(accounts.bob, 100).is_ok());
+            assert_eq!(erc20.decrease_allowance(accounts.bob, 40), Ok(60));
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 60);
+            assert_eq!(
+                erc20.decrease_allowance(accounts.bob, 61),
+                Err(Error::AllowanceTooLow)
+            );
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 60);
+            assert_eq!(erc20.decrease_allowance(accounts.bob, 60), Ok(0));
+            assert!(!erc20.allowances.contains((accounts.alice, accounts.bob)));
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+
+            let emitted_events = test::recorded_events().collect::<Vec<_>>();
+            let decoded = <Event as scale::Decode>::decode(
+                &mut &emitted_events.last().expect("no events").data[..],
+            )
+            .expect("decoded error");
+            match decoded {
+                Event::Approve(Approve { from, to, value }) => {
+                    assert_eq!(from, accounts.alice);
+                    assert_eq!(to, accounts.bob);
+                    assert_eq!(value, 0);
+                }
+                _ => panic!("Event do not match"),
+            }
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 1),
+                Err(Error::AllowanceTooLow)
+            );
+        }
+
+        #[ink::test]
+        fn rebase_scales_balances_and_keeps_shares() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 2_500).is_ok());
+
+            assert!(erc20.rebase(10_000).is_ok());
+            assert_eq!(erc20.total_supply(), 20_000);
+            assert_eq!(erc20.balance_of(accounts.alice), 15_000);
+            assert_eq!(erc20.balance_of(accounts.bob), 5_000);
+            assert_eq!(erc20.shares_of(accounts.alice), 7_500);
+            assert_eq!(erc20.shares_of(accounts.bob), 2_500);
+
+            assert!(erc20.rebase(-15_000).is_ok());
+            assert_eq!(erc20.total_supply(), 5_000);
+            assert_eq!(erc20.balance_of(accounts.alice), 3_750);
+            assert_eq!(erc20.balance_of(accounts.bob), 1_250);
+            assert_eq!(erc20.shares_of(accounts.alice), 7_500);
+            assert_eq!(erc20.shares_of(accounts.bob), 2_500);
+            assert_eq!(erc20.total_shares(), 10_000);
+        }
+
+        #[ink::test]
+        fn transfers_after_rebase_move_rebased_amounts() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 2_500).is_ok());
+            assert!(erc20.rebase(-5_000).is_ok());
+            assert!(erc20.transfer(accounts.bob, 750).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), 3_000);
+            assert_eq!(erc20.balance_of(accounts.bob), 2_000);
+            assert_eq!(erc20.shares_of(accounts.bob), 4_000);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.burn(2_000).is_ok());
+            assert_eq!(erc20.shares_of(accounts.bob), 0);
+            assert_eq!(erc20.total_supply(), 3_000);
+            assert_eq!(erc20.total_shares(), 6_000);
+            assert_eq!(erc20.rebase(1), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn rebase_to_zero_supply_fails() {
+            let mut erc20 = Erc20::new(10_000);
+            assert_eq!(erc20.rebase(-10_000), Err(Error::InvalidRebase));
+            assert_eq!(erc20.rebase(-10_001), Err(Error::Overflow));
+            assert_eq!(
+                mul_div(Balance::MAX, Balance::MAX, Balance::MAX),
+                Balance::MAX
+            );
+        }
+
+        #[ink::test]
+        fn transfer_receipt_matches_state_changes() {
+            let total_supply = 10_000;
+            let mut erc20 = Erc20::new(total_supply);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_fee(250, accounts.eve).is_ok());
+            test::set_block_timestamp::<DefaultEnvironment>(42);
+
+            let receipt = erc20
+                .transfer_receipt(accounts.bob, 1_000)
+                .expect("transfer failed");
+            assert_eq!(
+                receipt,
+                TransferReceipt {
+                    from: accounts.alice,
+                    to: accounts.bob,
+                    gross: 1_000,
+                    net: 975,
+                    fee: 25,
+                    timestamp: 42,
+                }
+            );
+            assert_eq!(
+                erc20.balance_of(accounts.alice),
+                total_supply - receipt.gross
+            );
+            assert_eq!(erc20.balance_of(accounts.bob), receipt.net);
+            assert_eq!(erc20.balance_of(accounts.eve), receipt.fee);
+
+            let receipt = erc20
+                .transfer_receipt(accounts.alice, 1_000)
+                .expect("transfer failed");
+            assert_eq!((receipt.gross, receipt.net, receipt.fee), (1_000, 1_000, 0));
+            assert_eq!(erc20.balance_of(accounts.alice), total_supply - 1_000);
+            assert_eq!(erc20.balance_of(accounts.eve), 25);
+        }
+
+        #[ink::test]
+        fn set_metadata_keeps_decimals() {
+            let mut erc20 = Erc20::new_with_metadata(
+                10_000,
+                Some(String::from("Token")),
+                Some(String::from("TKN")),
+                6,
+            );
+            assert_eq!(Erc20::new(10_000).token_decimals(), DEFAULT_DECIMALS);
+            assert_eq!(erc20.token_decimals(), 6);
+            assert!(erc20
+                .set_metadata(Some(String::from("Renamed")), Some(String::from("RNM")))
+                .is_ok());
+            assert_eq!(erc20.token_name(), Some(String::from("Renamed")));
+            assert_eq!(erc20.token_symbol(), Some(String::from("RNM")));
+            assert_eq!(erc20.token_decimals(), 6);
+
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.set_metadata(None, None), Err(Error::NotOwner));
+            assert_eq!(erc20.token_name(), Some(String::from("Renamed")));
+        }
+
+        /// Approve front-running: the owner tries to lower an allowance from
+        /// 100 to 50, but the spender gets a `transfer_from` in first.
+        #[ink::test]
+        fn approve_race_is_avoided_by_decrease_allowance() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+
+            // Unsafe: `approve` overwrites, so the spender ends up with 150.
+            let mut erc20 = Erc20::new(10_000);
+            assert!(erc20.approve(accounts.bob, 100).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.bob, 100)
+                .is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.approve(accounts.bob, 50).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.bob, 50)
+                .is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 150);
+
+            // Safe: the delta fails because the allowance is already spent.
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            let mut erc20 = Erc20::new(10_000);
+            assert!(erc20.approve(accounts.bob, 100).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.bob, 100)
+                .is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                erc20.decrease_allowance(accounts.bob, 50),
+                Err(Error::AllowanceTooLow)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 50),
+                Err(Error::AllowanceTooLow)
+            );
+            assert_eq!(erc20.balance_of(accounts.bob), 100);
+        }
+
+        #[ink::test]
+        fn claim_by_block_releases_after_unlock_block() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let unlock_block = ink::env::block_number::<DefaultEnvironment>() + 2;
+            assert!(erc20
+                .locked_transfer_by_block(accounts.bob, 300, unlock_block)
+                .is_ok());
+            assert!(erc20
+                .locked_transfer_by_block(accounts.bob, 200, unlock_block + 5)
+                .is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), 9_500);
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.claim_by_block(), Err(Error::NothingToClaim));
+            test::advance_block::<DefaultEnvironment>();
+            assert_eq!(erc20.claim_by_block(), Err(Error::NothingToClaim));
+            test::advance_block::<DefaultEnvironment>();
+            assert_eq!(erc20.claim_by_block(), Ok(300));
+            assert_eq!(erc20.balance_of(accounts.bob), 300);
+            assert_eq!(erc20.claim_by_block(), Err(Error::NothingToClaim));
+
+            for _ in 0..5 {
+                test::advance_block::<DefaultEnvironment>();
+            }
+            assert_eq!(erc20.claim_by_block(), Ok(200));
+            assert_eq!(erc20.balance_of(accounts.bob), 500);
+            assert!(!erc20.block_locks.contains(accounts.bob));
+        }
+
+        #[ink::test]
+        fn block_locks_are_merged_and_capped() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            for block in 0..MAX_BATCH as BlockNumber {
+                assert!(erc20
+                    .locked_transfer_by_block(accounts.bob, 1, 100 + block)
+                    .is_ok());
+            }
+            assert_eq!(
+                erc20.locked_transfer_by_block(accounts.bob, 1, 1_000),
+                Err(Error::TooManyLocks)
+            );
+            assert!(erc20.locked_transfer_by_block(accounts.bob, 5, 100).is_ok());
+            let locks = erc20.block_locks.get(accounts.bob).unwrap_or_default();
+            assert_eq!(locks.len(), MAX_BATCH);
+            assert_eq!(locks[0], (6, 100));
+        }
+
+        #[ink::test]
+        fn balances_labeled_pairs_accounts_with_balances() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 250).is_ok());
+            let query = vec![accounts.charlie, accounts.bob, accounts.alice, accounts.bob];
+            assert_eq!(
+                erc20.balances_labeled(query),
+                Ok(vec![
+                    (accounts.charlie, 0),
+                    (accounts.bob, 250),
+                    (accounts.alice, 9_750),
+                    (accounts.bob, 250),
+                ])
+            );
+            assert_eq!(erc20.balances_labeled(Vec::new()), Ok(Vec::new()));
+        }
+
+        #[ink::test]
+        fn detect_transfer_restriction_matches_transfer() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let code = |error: Error| error as u8 + 1;
+            let (alice, bob) = (accounts.alice, accounts.bob);
+            assert_eq!(erc20.detect_transfer_restriction(alice, bob, 100), 0);
+            assert_eq!(erc20.message_for_restriction_code(0), "no_restriction");
+
+            assert!(erc20.pause().is_ok());
+            assert_eq!(
+                erc20.detect_transfer_restriction(alice, bob, 100),
+                code(Error::Paused)
+            );
+            assert_eq!(erc20.transfer(bob, 100), Err(Error::Paused));
+            assert!(erc20.unpause().is_ok());
+
+            assert!(erc20.set_breaker_threshold(Some(500)).is_ok());
+            assert_eq!(
+                erc20.detect_transfer_restriction(alice, bob, 501),
+                code(Error::AboveBreakerThreshold)
+            );
+            assert!(erc20.set_breaker_threshold(None).is_ok());
+
+            assert!(erc20.set_allow_zero_transfers(false).is_ok());
+            assert_eq!(
+                erc20.detect_transfer_restriction(alice, bob, 0),
+                code(Error::ZeroTransfer)
+            );
+            assert_eq!(erc20.transfer(bob, 0), Err(Error::ZeroTransfer));
+
+            assert_eq!(
+                erc20.detect_transfer_restriction(alice, bob, 10_001),
+                code(Error::BalanceTooLow)
+            );
+            assert_eq!(erc20.transfer(bob, 10_001), Err(Error::BalanceTooLow));
+
+            assert!(erc20.set_max_known_accounts(1).is_ok());
+            assert_eq!(
+                erc20.detect_transfer_restriction(alice, bob, 100),
+                code(Error::TooManyAccounts)
+            );
+            assert_eq!(erc20.transfer(bob, 100), Err(Error::TooManyAccounts));
+            assert!(erc20
+                .set_max_known_accounts(DEFAULT_MAX_KNOWN_ACCOUNTS)
+                .is_ok());
+
+            assert!(erc20.set_bridge_account(alice, true).is_ok());
+            assert!(erc20.set_outflow_limit(Some((100, 1_000))).is_ok());
+            assert_eq!(
+                erc20.detect_transfer_restriction(alice, bob, 101),
+                code(Error::GlobalLimitExceeded)
+            );
+            assert_eq!(erc20.transfer(bob, 101), Err(Error::GlobalLimitExceeded));
+            assert_eq!(
+                erc20.message_for_restriction_code(code(Error::GlobalLimitExceeded)),
+                "global_limit_exceeded"
+            );
+
+            assert_eq!(erc20.detect_transfer_restriction(alice, bob, 100), 0);
+            assert!(erc20.transfer(bob, 100).is_ok());
+            assert_eq!(
+                erc20.message_for_restriction_code(u8::MAX),
+                "unknown_restriction"
+            );
+        }
+
+        #[ink::test]
+        fn account_storage_slots_tracks_balance_entry() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(erc20.account_storage_slots(accounts.bob), 0);
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            // The balance entry, the `known` marker and the incoming count.
+            assert_eq!(erc20.account_storage_slots(accounts.bob), 3);
+            assert!(erc20.set_exempt(accounts.bob, true).is_ok());
+            assert!(erc20.set_jurisdiction(accounts.bob, Some(1)).is_ok());
+            assert_eq!(erc20.account_storage_slots(accounts.bob), 5);
+            assert!(erc20.set_jurisdiction(accounts.bob, None).is_ok());
+            assert_eq!(erc20.account_storage_slots(accounts.bob), 4);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.transfer(accounts.alice, 100).is_ok());
+            // The balance entry is gone, the outgoing count is new.
+            assert_eq!(erc20.account_storage_slots(accounts.bob), 4);
+            assert!(erc20.approve(accounts.charlie, 5).is_ok());
+            assert_eq!(erc20.account_storage_slots(accounts.bob), 5);
+        }
+
+        #[ink::test]
+        fn initialize_works_only_once() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mut erc20 = Erc20::new_uninitialized();
+            assert_eq!(erc20.total_supply(), 0);
+            assert_eq!(erc20.owner(), None);
+
+            test::set_caller::<DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                erc20.initialize(1_000, accounts.django),
+                Err(Error::NotOwner)
+            );
+            assert_eq!(erc20.owner(), None);
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.initialize(1_000, accounts.bob).is_ok());
+            assert_eq!(erc20.total_supply(), 1_000);
+            assert_eq!(erc20.balance_of(accounts.bob), 1_000);
+            assert_eq!(erc20.owner(), Some(accounts.bob));
+
+            assert_eq!(
+                erc20.initialize(5_000, accounts.django),
+                Err(Error::AlreadyInitialized)
+            );
+            assert_eq!(erc20.total_supply(), 1_000);
+            assert_eq!(erc20.owner(), Some(accounts.bob));
+
+            let mut erc20 = Erc20::new(10_000);
+            assert_eq!(
+                erc20.initialize(5_000, accounts.django),
+                Err(Error::AlreadyInitialized)
+            );
+        }
+
+        #[ink::test]
+        fn simulate_transfer_projects_balances() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 1_000).is_ok());
+            assert_eq!(
+                erc20.simulate_transfer(accounts.alice, accounts.bob, 400),
+                (8_600, 1_400)
+            );
+            assert_eq!(
+                erc20.simulate_transfer(accounts.bob, accounts.bob, 400),
+                (1_000, 1_000)
+            );
+            assert_eq!(erc20.balance_of(accounts.alice), 9_000);
+            assert_eq!(erc20.balance_of(accounts.bob), 1_000);
+
+            assert!(erc20.set_fee(1_000, accounts.charlie).is_ok());
+            let projected = erc20.simulate_transfer(accounts.alice, accounts.bob, 1_000);
+            assert_eq!(projected, (8_000, 1_900));
+            assert!(erc20.transfer(accounts.bob, 1_000).is_ok());
+            assert_eq!(
+                (
+                    erc20.balance_of(accounts.alice),
+                    erc20.balance_of(accounts.bob)
+                ),
+                projected
+            );
+        }
+
+        #[ink::test]
+        fn trading_opens_at_launch_time() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 1_000).is_ok());
+            assert!(erc20.transfer(accounts.charlie, 1_000).is_ok());
+            assert!(erc20.enable_trading_at(100).is_ok());
+            assert!(erc20
+                .set_trading_whitelisted(accounts.charlie, true)
+                .is_ok());
+
+            test::set_block_timestamp::<DefaultEnvironment>(99);
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer(accounts.django, 100),
+                Err(Error::TradingNotStarted)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert!(erc20.transfer(accounts.django, 100).is_ok());
+
+            test::set_block_timestamp::<DefaultEnvironment>(100);
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.transfer(accounts.django, 100).is_ok());
+            assert_eq!(erc20.balance_of(accounts.django), 200);
+
+            assert_eq!(erc20.enable_trading_at(0), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn rich_events_report_post_transfer_balances() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            assert_eq!(test::recorded_events().count(), 2);
+
+            assert!(erc20.set_rich_events(true).is_ok());
+            assert!(erc20.transfer(accounts.bob, 400).is_ok());
+            let emitted_events = test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(emitted_events.len(), 4);
+            let decoded = <Event as scale::Decode>::decode(&mut &emitted_events[3].data[..])
+                .expect("decoded error");
+            match decoded {
+                Event::TransferWithBalances(TransferWithBalances {
+                    from,
+                    to,
+                    value,
+                    from_balance,
+                    to_balance,
+                }) => {
+                    assert_eq!(from, accounts.alice);
+                    assert_eq!(to, accounts.bob);
+                    assert_eq!(value, 400);
+                    assert_eq!(from_balance, 9_500);
+                    assert_eq!(to_balance, 500);
+                }
+                _ => panic!("Event do not match"),
+            }
+        }
+
+        #[ink::test]
+        fn revoke_allowances_zeroes_each_spender() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let spenders = vec![accounts.bob, accounts.charlie, accounts.django];
+            for spender in &spenders {
+                assert!(erc20.approve(*spender, 100).is_ok());
+            }
+            assert_eq!(erc20.spender_count(accounts.alice), 3);
+
+            assert!(erc20.revoke_allowances(spenders.clone()).is_ok());
+            for spender in &spenders {
+                assert_eq!(erc20.allowance(accounts.alice, *spender), 0);
+                assert!(!erc20.allowances.contains((accounts.alice, *spender)));
+            }
+            assert_eq!(erc20.spender_count(accounts.alice), 0);
+            // One `Transfer` from the constructor, three approvals, three revocations.
+            assert_eq!(test::recorded_events().count(), 7);
+        }
+
+        #[ink::test]
+        fn supply_history_records_mint_and_burn() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.supply_history().is_empty());
+            test::set_block_timestamp::<DefaultEnvironment>(10);
+            assert!(erc20.mint(accounts.bob, 500).is_ok());
+            test::set_block_timestamp::<DefaultEnvironment>(20);
+            assert!(erc20.burn(200).is_ok());
+            assert_eq!(erc20.supply_history(), vec![(10, 10_500), (20, 10_300)]);
+
+            for _ in 0..MAX_SUPPLY_HISTORY {
+                assert!(erc20.burn(1).is_ok());
+            }
+            let history = erc20.supply_history();
+            assert_eq!(history.len(), MAX_SUPPLY_HISTORY);
+            assert_eq!(history[0], (20, 10_299));
+            assert_eq!(history[MAX_SUPPLY_HISTORY - 1], (20, 10_236));
+        }
+
+        #[ink::test]
+        fn mutating_messages_require_initialize() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mut erc20 = Erc20::new_uninitialized();
+            let bob = accounts.bob;
+            assert_eq!(erc20.transfer(bob, 0), Err(Error::NotInitialized));
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, bob, 0),
+                Err(Error::NotInitialized)
+            );
+            assert_eq!(erc20.approve(bob, 10), Err(Error::NotInitialized));
+            assert_eq!(erc20.mint(bob, 10), Err(Error::NotInitialized));
+            assert_eq!(erc20.burn(0), Err(Error::NotInitialized));
+            assert_eq!(erc20.transfer_receipt(bob, 0), Err(Error::NotInitialized));
+            assert_eq!(
+                erc20.transfer_split(bob, accounts.charlie, 0, 5_000),
+                Err(Error::NotInitialized)
+            );
+            assert_eq!(erc20.stake(0), Err(Error::NotInitialized));
+            assert_eq!(erc20.claim_mint(), Err(Error::NotInitialized));
+            assert_eq!(
+                erc20.recover_account(accounts.alice, bob),
+                Err(Error::NotInitialized)
+            );
+            assert_eq!(erc20.set_operator(bob, true), Err(Error::NotInitialized));
+            assert_eq!(erc20.create_pool(vec![bob], 10), Err(Error::NotInitialized));
+            assert_eq!(erc20.prepare_transfer(bob, 10), Err(Error::NotInitialized));
+            assert_eq!(
+                erc20.permit(accounts.alice, bob, 10, 0, [0; 65]),
+                Err(Error::NotInitialized)
+            );
+            assert_eq!(
+                erc20.revoke_allowances(vec![bob]),
+                Err(Error::NotInitialized)
+            );
+            assert_eq!(erc20.revoke_all_allowances(), Err(Error::NotInitialized));
+            assert_eq!(
+                erc20.set_guardians(vec![bob], 1),
+                Err(Error::NotInitialized)
+            );
+            assert_eq!(erc20.unstake(0), Err(Error::NotInitialized));
+            assert_eq!(erc20.balance_of(accounts.alice), 0);
+
+            assert!(erc20.initialize(1_000, accounts.alice).is_ok());
+            assert!(erc20.transfer(bob, 100).is_ok());
+            assert!(erc20.approve(bob, 10).is_ok());
+            assert!(erc20.mint(bob, 10).is_ok());
+            assert!(erc20.burn(100).is_ok());
+            test::set_caller::<DefaultEnvironment>(bob);
+            assert!(erc20.transfer_from(accounts.alice, bob, 10).is_ok());
+            assert_eq!(erc20.balance_of(bob), 120);
+        }
+
+        #[ink::test]
+        fn is_owner_works() {
+            let erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.is_owner(accounts.alice));
+            assert!(!erc20.is_owner(accounts.eve));
+            assert!(!Erc20::new_uninitialized().is_owner(accounts.alice));
+        }
+
+        #[ink::test]
+        fn timelocked_actions_wait_for_min_delay() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_min_delay(100).is_ok());
+            assert_eq!(erc20.mint(accounts.bob, 1), Err(Error::TimelockNotElapsed));
+            assert_eq!(erc20.pause(), Err(Error::TimelockNotElapsed));
+            assert_eq!(erc20.set_min_delay(0), Err(Error::TimelockNotElapsed));
+
+            test::set_block_timestamp::<DefaultEnvironment>(1_000);
+            let mint = erc20.queue_action(AdminAction::Mint(accounts.bob, 500));
+            assert_eq!(mint, Ok(0));
+            let pause = erc20.queue_action(AdminAction::Pause).expect("queued");
+            assert_eq!(
+                erc20.queued_action(pause),
+                Some((AdminAction::Pause, 1_100))
+            );
+
+            test::set_block_timestamp::<DefaultEnvironment>(1_099);
+            assert_eq!(erc20.execute_action(0), Err(Error::TimelockNotElapsed));
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+
+            test::set_block_timestamp::<DefaultEnvironment>(1_100);
+            assert!(erc20.execute_action(0).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 500);
+            assert_eq!(erc20.execute_action(0), Err(Error::ActionNotFound));
+            assert!(erc20.execute_action(pause).is_ok());
+            assert!(erc20.paused());
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.queue_action(AdminAction::Pause), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn transfer_counts_track_both_directions() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            assert!(erc20.transfer(accounts.charlie, 100).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.transfer(accounts.alice, 50).is_ok());
+            assert!(erc20.transfer(accounts.bob, 50).is_ok());
+            assert_eq!(
+                erc20.transfer(accounts.alice, 1_000),
+                Err(Error::BalanceTooLow)
+            );
+
+            assert_eq!(erc20.transfer_counts(accounts.alice), (3, 1));
+            assert_eq!(erc20.transfer_counts(accounts.bob), (1, 2));
+            assert_eq!(erc20.transfer_counts(accounts.charlie), (0, 1));
+            assert_eq!(erc20.transfer_counts(accounts.django), (0, 0));
+        }
+
+        #[ink::test]
+        fn try_transfer_reports_both_outcomes() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(
+                erc20.try_transfer(accounts.bob, 100),
+                TransferOutcome::Success
+            );
+            assert_eq!(
+                erc20.try_transfer(accounts.bob, 10_000),
+                TransferOutcome::Failed(Error::BalanceTooLow)
+            );
+            assert_eq!(erc20.balance_of(accounts.bob), 100);
+
+            let emitted_events = test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(emitted_events.len(), 3);
+            let decoded = <Event as scale::Decode>::decode(&mut &emitted_events[1].data[..])
+                .expect("decoded error");
+            match decoded {
+                Event::Transfer(Transfer {
+                    from, to, value, ..
+                }) => {
+                    assert_eq!(from, Some(accounts.alice));
+                    assert_eq!(to, accounts.bob);
+                    assert_eq!(value, 100);
+                }
+                _ => panic!("Event do not match"),
+            }
+            let decoded = <Event as scale::Decode>::decode(&mut &emitted_events[2].data[..])
+                .expect("decoded error");
+            match decoded {
+                Event::TransferFailed(TransferFailed {
+                    from,
+                    to,
+                    value,
+                    reason,
+                }) => {
+                    assert_eq!(from, accounts.alice);
+                    assert_eq!(to, accounts.bob);
+                    assert_eq!(value, 10_000);
+                    assert_eq!(reason, Error::BalanceTooLow);
+                }
+                _ => panic!("Event do not match"),
+            }
+        }
+
+        #[ink::test]
+        fn period_allowance_resets_each_period() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let day = 86_400_000;
+            test::set_block_timestamp::<DefaultEnvironment>(1_000);
+            assert!(erc20.approve_per_period(accounts.bob, 10, day).is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.transfer_from(accounts.alice, accounts.bob, 6).is_ok());
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 5),
+                Err(Error::PeriodLimitExceeded)
+            );
+            assert!(erc20.transfer_from(accounts.alice, accounts.bob, 4).is_ok());
+
+            test::set_block_timestamp::<DefaultEnvironment>(1_000 + day - 1);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 1),
+                Err(Error::PeriodLimitExceeded)
+            );
+            test::set_block_timestamp::<DefaultEnvironment>(1_000 + day);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.bob, 10)
+                .is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 20);
+            assert_eq!(
+                erc20.period_allowance(accounts.alice, accounts.bob),
+                Some((10, day, 10, 1_000 + day))
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.approve_per_period(accounts.bob, 0, day).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 1),
+                Err(Error::AllowanceTooLow)
+            );
+        }
+
+        #[ink::test]
+        fn approve_per_period_rejects_zero_period() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(
+                erc20.approve_per_period(accounts.bob, 100, 0),
+                Err(Error::InvalidPeriod)
+            );
+            assert_eq!(erc20.period_allowance(accounts.alice, accounts.bob), None);
+            assert!(erc20.approve_per_period(accounts.bob, 0, 0).is_ok());
+        }
+
+        #[ink::test]
+        fn checkpoint_supply_flags_mismatch() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 400).is_ok());
+            let holders = vec![accounts.alice, accounts.bob];
+            assert!(erc20.checkpoint_supply(holders.clone()).is_ok());
+            // A repeated holder is not counted twice.
+            assert!(erc20
+                .checkpoint_supply(vec![accounts.alice, accounts.bob, accounts.bob])
+                .is_ok());
+
+            // Credit bob without touching the supply, as a double-credit bug would.
+            erc20.balances.insert(accounts.bob, &900);
+            assert_eq!(erc20.checkpoint_supply(holders), Err(Error::SupplyMismatch));
+            let emitted_events = test::recorded_events().collect::<Vec<_>>();
+            let decoded = <Event as scale::Decode>::decode(
+                &mut &emitted_events.last().expect("no events").data[..],
+            )
+            .expect("decoded error");
+            match decoded {
+                Event::SupplyMismatch(SupplyMismatch { expected, actual }) => {
+                    assert_eq!(expected, 10_000);
+                    assert_eq!(actual, 10_500);
+                }
+                _ => panic!("Event do not match"),
+            }
+        }
+
+        #[ink::test]
+        fn checkpoint_supply_holds_after_uneven_rebase() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 1).is_ok());
+            assert!(erc20.transfer(accounts.charlie, 1).is_ok());
+            assert!(erc20.rebase(1).is_ok());
+            let holders = vec![accounts.alice, accounts.bob, accounts.charlie];
+            // The rounded-down balances no longer add up to the supply.
+            let balances: Balance = holders.iter().map(|who| erc20.balance_of(*who)).sum();
+            assert!(balances < erc20.total_supply());
+            assert!(erc20.checkpoint_supply(holders).is_ok());
+        }
+
+        #[ink::test]
+        fn approval_age_updates_on_each_approve() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(erc20.approval_age(accounts.alice, accounts.bob), None);
+            test::set_block_timestamp::<DefaultEnvironment>(100);
+            assert!(erc20.approve(accounts.bob, 50).is_ok());
+            assert_eq!(erc20.approval_age(accounts.alice, accounts.bob), Some(100));
+            test::set_block_timestamp::<DefaultEnvironment>(200);
+            assert!(erc20.increase_allowance(accounts.bob, 50).is_ok());
+            assert_eq!(erc20.approval_age(accounts.alice, accounts.bob), Some(200));
+            test::set_block_timestamp::<DefaultEnvironment>(300);
+            assert!(erc20.decrease_allowance(accounts.bob, 100).is_ok());
+            assert_eq!(erc20.approval_age(accounts.alice, accounts.bob), None);
+            assert!(!erc20.approved_at.contains((accounts.alice, accounts.bob)));
+        }
+
+        #[ink::test]
+        fn distribution_phase_limits_senders_and_recipients() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.charlie, 1_000).is_ok());
+            assert!(erc20.set_fee(0, accounts.django).is_ok());
+            assert!(erc20.transfer(accounts.django, 1_000).is_ok());
+            assert!(erc20.set_distribution_phase(true).is_ok());
+            assert!(erc20.set_distribution_recipient(accounts.bob, true).is_ok());
+
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            assert_eq!(
+                erc20.transfer(accounts.eve, 100),
+                Err(Error::DistributionPhaseActive)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.django);
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                erc20.transfer(accounts.eve, 100),
+                Err(Error::DistributionPhaseActive)
+            );
+            assert_eq!(
+                erc20.transfer(accounts.bob, 100),
+                Err(Error::DistributionPhaseActive)
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.set_distribution_phase(false).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert!(erc20.transfer(accounts.eve, 100).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 200);
+        }
+
+        #[ink::test]
+        fn decrease_allowance_to_zero_saturates() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve(accounts.bob, 100).is_ok());
+            assert_eq!(
+                erc20.decrease_allowance(accounts.bob, 150),
+                Err(Error::AllowanceTooLow)
+            );
+            assert!(erc20.decrease_allowance_to_zero(accounts.bob).is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+            assert_eq!(erc20.spender_count(accounts.alice), 0);
+            assert!(erc20.decrease_allowance_to_zero(accounts.bob).is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+        }
+
+        /// Rebuilds balances and allowances from the recorded `Transfer` and
+        /// `Approve` events alone, the way an indexer would.
+        fn replay_events() -> (
+            std::collections::HashMap<AccountId, Balance>,
+            std::collections::HashMap<(AccountId, AccountId), Balance>,
+        ) {
+            let mut balances = std::collections::HashMap::new();
+            let mut allowances = std::collections::HashMap::new();
+            for event in test::recorded_events() {
+                match <Event as scale::Decode>::decode(&mut &event.data[..]).expect("decoded error")
+                {
+                    Event::Transfer(Transfer {
+                        from, to, value, ..
+                    }) => {
+                        if let Some(from) = from {
+                            *balances.entry(from).or_insert(0) -= value;
+                        }
+                        *balances.entry(to).or_insert(0) += value;
+                    }
+                    Event::Approve(Approve { from, to, value }) => {
+                        allowances.insert((from, to), value);
+                    }
+                    _ => {}
+                }
+            }
+            (balances, allowances)
+        }
+
+        #[ink::test]
+        fn events_replay_to_contract_state() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.mint(accounts.bob, 1_000).is_ok());
+            assert!(erc20.transfer(accounts.charlie, 700).is_ok());
+            assert!(erc20.approve(accounts.bob, 500).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.django, 200)
+                .is_ok());
+            assert!(erc20.approve(accounts.charlie, 50).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert!(erc20.transfer_from(accounts.bob, accounts.eve, 50).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.burn(100).is_ok());
+            assert!(erc20.increase_allowance(accounts.charlie, 30).is_ok());
+
+            let (balances, allowances) = replay_events();
+            let holders = [
+                accounts.alice,
+                accounts.bob,
+                accounts.charlie,
+                accounts.django,
+                accounts.eve,
+                accounts.frank,
+            ];
+            for who in holders {
+                assert_eq!(
+                    balances.get(&who).copied().unwrap_or_default(),
+                    erc20.balance_of(who)
+                );
+                for spender in holders {
+                    assert_eq!(
+                        allowances.get(&(who, spender)).copied().unwrap_or_default(),
+                        erc20.allowance(who, spender)
+                    );
+                }
+            }
+            let burned = balances[&zero_account()];
+            assert_eq!(burned, 100);
+            let supply: Balance = holders.iter().filter_map(|who| balances.get(who)).sum();
+            assert_eq!(supply, erc20.total_supply());
+        }
+
+        #[ink::test]
+        fn transfer_from_is_capped_by_balance_after_burn() {
+            let mut erc20 = Erc20::new(1_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve(accounts.bob, 800).is_ok());
+            assert!(erc20.burn(900).is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 800),
+                Err(Error::BalanceTooLow)
+            );
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 800);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.bob, 100)
+                .is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 700);
+        }
+
+        #[ink::test]
+        fn supply_policy_guards_mint_burn_and_rebase() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_supply_bounds(Some(9_000), Some(11_000)).is_ok());
+
+            assert_eq!(erc20.mint(accounts.bob, 1_001), Err(Error::AboveMaxSupply));
+            assert!(erc20.mint(accounts.bob, 1_000).is_ok());
+            assert_eq!(erc20.rebase(1), Err(Error::AboveMaxSupply));
+
+            assert_eq!(erc20.burn(2_001), Err(Error::BelowMinSupply));
+            assert!(erc20.burn(2_000).is_ok());
+            assert_eq!(erc20.rebase(-1), Err(Error::BelowMinSupply));
+            assert_eq!(erc20.total_supply(), 9_000);
+
+            assert!(erc20.set_supply_bounds(None, None).is_ok());
+            assert_eq!(erc20.mint(accounts.bob, Balance::MAX), Err(Error::Overflow));
+            assert!(erc20.freeze_supply().is_ok());
+            assert_eq!(erc20.mint(accounts.bob, 1), Err(Error::SupplyFrozen));
+            assert_eq!(erc20.burn(1), Err(Error::SupplyFrozen));
+            assert_eq!(erc20.rebase(1), Err(Error::SupplyFrozen));
+        }
+
+        #[ink::test]
+        fn total_balance_of_includes_unvested_tokens() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 500).is_ok());
+            assert!(erc20.vest(accounts.bob, 1_000, 100, 200).is_ok());
+            assert_eq!(
+                erc20.vest(accounts.bob, 1_000, 100, 200),
+                Err(Error::VestingExists)
+            );
+            assert_eq!(
+                erc20.vest(accounts.charlie, 1_000, 200, 200),
+                Err(Error::InvalidSchedule)
+            );
+            assert_eq!(erc20.balance_of(accounts.bob), 500);
+            assert_eq!(erc20.total_balance_of(accounts.bob), 1_500);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.release_vested(), Err(Error::NothingToClaim));
+            test::set_block_timestamp::<DefaultEnvironment>(125);
+            assert_eq!(erc20.vested_amount(accounts.bob), 250);
+            assert_eq!(erc20.release_vested(), Ok(250));
+            assert_eq!(erc20.balance_of(accounts.bob), 750);
+            assert_eq!(erc20.total_balance_of(accounts.bob), 1_500);
+
+            test::set_block_timestamp::<DefaultEnvironment>(300);
+            assert_eq!(erc20.release_vested(), Ok(750));
+            assert_eq!(erc20.balance_of(accounts.bob), 1_500);
+            assert_eq!(erc20.total_balance_of(accounts.bob), 1_500);
+            assert!(!erc20.vesting.contains(accounts.bob));
+        }
+
+        #[ink::test]
+        fn only_owner_vests_for_other_accounts() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 500).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.vest(accounts.charlie, 1, 0, u64::MAX),
+                Err(Error::NotOwner)
+            );
+            assert!(erc20.vest(accounts.bob, 100, 0, 100).is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.mint_vested(accounts.charlie, 1_000, 0, 100).is_ok());
+            assert_eq!(erc20.total_balance_of(accounts.charlie), 1_000);
+        }
+
+        #[ink::test]
+        fn zero_account_is_all_zero_bytes() {
+            let erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(zero_account(), AccountId::from([0; 32]));
+            assert!(erc20.is_zero_account(AccountId::from([0; 32])));
+            assert!(!erc20.is_zero_account(accounts.alice));
+        }
+
+        #[ink::test]
+        fn grant_allows_one_transfer_per_recipient() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.grant(accounts.bob, 100).is_ok());
+            assert_eq!(erc20.grant(accounts.bob, 100), Err(Error::AlreadyGranted));
+            assert!(erc20.grant(accounts.charlie, 100).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 100);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.reset_grant(accounts.alice, accounts.bob),
+                Err(Error::NotOwner)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.reset_grant(accounts.alice, accounts.bob).is_ok());
+            assert!(erc20.grant(accounts.bob, 100).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 200);
+        }
+
+        #[ink::test]
+        fn transfer_from_batch_spends_each_allowance() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 1_000).is_ok());
+            assert!(erc20.approve(accounts.charlie, 300).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.approve(accounts.charlie, 200).is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            let batch = vec![
+                (accounts.alice, accounts.django, 100),
+                (accounts.bob, accounts.django, 200),
+                (accounts.alice, accounts.eve, 200),
+            ];
+            assert!(erc20.transfer_from_batch(batch).is_ok());
+            assert_eq!(erc20.balance_of(accounts.django), 300);
+            assert_eq!(erc20.balance_of(accounts.eve), 200);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.charlie), 0);
+            assert_eq!(erc20.allowance(accounts.bob, accounts.charlie), 0);
+        }
+
+        #[ink::test]
+        fn transfer_from_batch_failure_moves_nothing() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 1_000).is_ok());
+            assert!(erc20.approve(accounts.charlie, 300).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.approve(accounts.charlie, 200).is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            let batch = vec![
+                (accounts.alice, accounts.django, 100),
+                (accounts.bob, accounts.django, 150),
+                (accounts.bob, accounts.eve, 100),
+            ];
+            assert_eq!(
+                erc20.transfer_from_batch(batch),
+                Err(Error::AllowanceTooLow)
+            );
+            assert_eq!(erc20.balance_of(accounts.django), 0);
+            assert_eq!(erc20.balance_of(accounts.eve), 0);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.charlie), 300);
+            assert_eq!(erc20.allowance(accounts.bob, accounts.charlie), 200);
+        }
+
+        #[ink::test]
+        fn transfer_whole_scales_by_decimals() {
+            let unit = 10u128.pow(18);
+            let mut erc20 = Erc20::new(100 * unit);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer_whole(accounts.bob, 5).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 5 * unit);
+            assert_eq!(
+                erc20.transfer_whole(accounts.bob, u128::MAX / unit + 1),
+                Err(Error::Overflow)
+            );
+
+            // 10^40 base units per token does not fit in a `u128`.
+            let mut erc20 = Erc20::new_with_metadata(1_000, None, None, 40);
+            assert_eq!(erc20.transfer_whole(accounts.bob, 1), Err(Error::Overflow));
+        }
+
+        #[ink::test]
+        fn frozen_amount_blocks_only_frozen_portion() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 1_000).is_ok());
+            assert!(erc20.freeze_amount(accounts.bob, 300).is_ok());
+            assert_eq!(erc20.frozen_of(accounts.bob), 300);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer(accounts.charlie, 701),
+                Err(Error::BalanceFrozen)
+            );
+            assert_eq!(
+                erc20.transfer(accounts.charlie, 1_001),
+                Err(Error::BalanceTooLow)
+            );
+            assert!(erc20.transfer(accounts.charlie, 700).is_ok());
+            assert_eq!(
+                erc20.transfer(accounts.charlie, 1),
+                Err(Error::BalanceFrozen)
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.unfreeze_amount(accounts.bob, 100).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.transfer(accounts.charlie, 100).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 200);
+            assert_eq!(erc20.frozen_of(accounts.bob), 200);
+        }
+
+        #[ink::test]
+        fn genesis_allocation_records_initial_mints() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let erc20 = Erc20::new(10_000);
+            assert_eq!(erc20.genesis_allocation(), vec![(accounts.alice, 10_000)]);
+
+            let allocations = vec![
+                (accounts.bob, 600),
+                (accounts.charlie, 300),
+                (accounts.bob, 100),
+            ];
+            let erc20 = Erc20::new_with_allocations(allocations.clone());
+            assert_eq!(erc20.genesis_allocation(), allocations);
+            assert_eq!(erc20.total_supply(), 1_000);
+            assert_eq!(erc20.balance_of(accounts.bob), 700);
+            assert_eq!(erc20.balance_of(accounts.charlie), 300);
+            assert_eq!(erc20.owner(), Some(accounts.alice));
+            assert!(Erc20::new_uninitialized().genesis_allocation().is_empty());
+        }
+
+        #[ink::test]
+        fn allowance_history_spans_revocation_epochs() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve(accounts.bob, 100).is_ok());
+            assert!(erc20.approve(accounts.charlie, 10).is_ok());
+            assert!(erc20.revoke_all_allowances().is_ok());
+            assert_eq!(erc20.allowance_epoch(accounts.alice), 1);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.charlie), 0);
+            assert_eq!(erc20.spender_count(accounts.alice), 0);
+
+            assert!(erc20.approve(accounts.bob, 40).is_ok());
+            assert!(erc20.increase_allowance(accounts.bob, 10).is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 50);
+            assert_eq!(erc20.spender_count(accounts.alice), 1);
+            assert_eq!(
+                erc20.allowance_history(accounts.alice, accounts.bob),
+                vec![(0, 100), (1, 50)]
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.charlie, 1),
+                Err(Error::AllowanceTooLow)
+            );
+        }
+
+        #[ink::test]
+        fn exempt_accounts_transfer_while_paused() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 1_000).is_ok());
+            assert!(erc20.transfer(accounts.charlie, 1_000).is_ok());
+            assert!(erc20.set_exempt(accounts.bob, true).is_ok());
+            assert!(erc20.set_bridge_account(accounts.bob, true).is_ok());
+            assert!(erc20.set_outflow_limit(Some((1, 1_000))).is_ok());
+            assert!(erc20.pause().is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.transfer(accounts.django, 500).is_ok());
+            assert_eq!(
+                erc20.transfer(accounts.django, 501),
+                Err(Error::BalanceTooLow)
+            );
+            assert!(erc20.approve(accounts.eve, 100).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(erc20.transfer(accounts.django, 500), Err(Error::Paused));
+            assert!(erc20.approve(accounts.eve, 100).is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert!(erc20.transfer_from(accounts.bob, accounts.eve, 100).is_ok());
+            assert_eq!(
+                erc20.transfer_from(accounts.charlie, accounts.eve, 100),
+                Err(Error::Paused)
+            );
+            assert_eq!(erc20.balance_of(accounts.django), 500);
+            assert_eq!(erc20.balance_of(accounts.eve), 100);
+        }
+
+        #[ink::test]
+        fn migration_info_reports_versions() {
+            let erc20 = Erc20::new(10_000);
+            assert_eq!(erc20.migration_info(), (LOGIC_VERSION, STORAGE_VERSION));
+            let erc20 = Erc20::new_uninitialized();
+            assert_eq!(erc20.migration_info(), (LOGIC_VERSION, STORAGE_VERSION));
+        }
+
+        #[ink::test]
+        fn transfer_precheck_reports_each_failure() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let (alice, bob) = (accounts.alice, accounts.bob);
+            assert_eq!(
+                Erc20::new_uninitialized().transfer_precheck(alice, bob, 1),
+                Err(Error::NotInitialized)
+            );
+
+            let mut erc20 = Erc20::new(10_000);
+            assert_eq!(erc20.transfer_precheck(alice, bob, 100), Ok(()));
+            let fails_with = |erc20: &mut Erc20, value: Balance, error: Error| {
+                assert_eq!(erc20.transfer_precheck(alice, bob, value), Err(error));
+                assert_eq!(erc20.transfer(bob, value), Err(error));
+            };
+
+            assert!(erc20.pause().is_ok());
+            fails_with(&mut erc20, 100, Error::Paused);
+            assert!(erc20.unpause().is_ok());
+
+            assert!(erc20.set_breaker_threshold(Some(50)).is_ok());
+            assert_eq!(
+                erc20.transfer_precheck(alice, bob, 51),
+                Err(Error::AboveBreakerThreshold)
+            );
+            assert!(erc20.set_breaker_threshold(None).is_ok());
+
+            assert!(erc20.set_allow_zero_transfers(false).is_ok());
+            fails_with(&mut erc20, 0, Error::ZeroTransfer);
+            fails_with(&mut erc20, 10_001, Error::BalanceTooLow);
+
+            assert!(erc20.freeze_amount(alice, 9_950).is_ok());
+            fails_with(&mut erc20, 100, Error::BalanceFrozen);
+            assert!(erc20.unfreeze_amount(alice, 9_950).is_ok());
+
+            assert!(erc20.set_distribution_phase(true).is_ok());
+            fails_with(&mut erc20, 100, Error::DistributionPhaseActive);
+            assert!(erc20.set_distribution_phase(false).is_ok());
+
+            assert!(erc20.set_bridge_account(alice, true).is_ok());
+            assert!(erc20.set_outflow_limit(Some((10, 1_000))).is_ok());
+            fails_with(&mut erc20, 11, Error::GlobalLimitExceeded);
+            assert!(erc20.set_outflow_limit(None).is_ok());
+
+            assert!(erc20.set_max_known_accounts(1).is_ok());
+            fails_with(&mut erc20, 100, Error::TooManyAccounts);
+            assert!(erc20
+                .set_max_known_accounts(DEFAULT_MAX_KNOWN_ACCOUNTS)
+                .is_ok());
+
+            assert!(erc20.transfer(bob, 100).is_ok());
+            assert!(erc20.enable_trading_at(100).is_ok());
+            test::set_caller::<DefaultEnvironment>(bob);
+            assert_eq!(
+                erc20.transfer_precheck(bob, alice, 1),
+                Err(Error::TradingNotStarted)
+            );
+            assert_eq!(erc20.transfer(alice, 1), Err(Error::TradingNotStarted));
+        }
+
+        #[ink::test]
+        fn staked_tokens_are_not_transferable() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 1_000).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.stake(1_001), Err(Error::BalanceTooLow));
+            assert!(erc20.stake(600).is_ok());
+            assert_eq!(erc20.staked_of(accounts.bob), 600);
+            assert_eq!(erc20.balance_of(accounts.bob), 1_000);
+
+            assert_eq!(
+                erc20.transfer(accounts.charlie, 401),
+                Err(Error::BalanceStaked)
+            );
+            assert!(erc20.transfer(accounts.charlie, 400).is_ok());
+            assert_eq!(erc20.unstake(601), Err(Error::BalanceTooLow));
+            assert!(erc20.unstake(600).is_ok());
+            assert_eq!(erc20.staked_of(accounts.bob), 0);
+            assert!(erc20.transfer(accounts.charlie, 600).is_ok());
+            assert_eq!(erc20.balance_of(accounts.charlie), 1_000);
+        }
+
+        #[ink::test]
+        fn permit_rejects_signature_for_other_domain() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let (secret, owner) = ecdsa_account(0x11);
+            let domain = erc20.domain_separator();
+            assert_eq!(erc20.domain_separator(), domain);
+
+            let hash = erc20.permit_hash(owner, accounts.bob, 400, 0, 100);
+            let signature = ecdsa_sign(&secret, hash);
+            assert!(erc20.set_chain_id(2).is_ok());
+            assert_ne!(erc20.domain_separator(), domain);
+            assert_eq!(
+                erc20.permit(owner, accounts.bob, 400, 100, signature),
+                Err(Error::InvalidSignature)
+            );
+
+            let hash = erc20.permit_hash(owner, accounts.bob, 400, 0, 100);
+            let signature = ecdsa_sign(&secret, hash);
+            assert!(erc20
+                .permit(owner, accounts.bob, 400, 100, signature)
+                .is_ok());
+            assert_eq!(erc20.allowance(owner, accounts.bob), 400);
+        }
+
+        #[ink::test]
+        fn mint_vested_adds_supply_locked_until_vested() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.mint_vested(accounts.bob, 1_000, 100, 200).is_ok());
+            assert_eq!(erc20.total_supply(), 11_000);
+            assert_eq!(erc20.balance_of(accounts.alice), 10_000);
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+            assert_eq!(erc20.total_balance_of(accounts.bob), 1_000);
+            assert_eq!(
+                erc20.mint_vested(accounts.bob, 1_000, 100, 200),
+                Err(Error::VestingExists)
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer(accounts.charlie, 1),
+                Err(Error::BalanceTooLow)
+            );
+            assert_eq!(
+                erc20.mint_vested(accounts.charlie, 1_000, 100, 200),
+                Err(Error::NotOwner)
+            );
+            test::set_block_timestamp::<DefaultEnvironment>(150);
+            assert_eq!(erc20.release_vested(), Ok(500));
+            assert!(erc20.transfer(accounts.charlie, 500).is_ok());
+            assert_eq!(erc20.total_supply(), 11_000);
+        }
+
+        #[ink::test]
+        fn operator_moves_any_amount_until_revoked() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_operator(accounts.bob, true).is_ok());
+            assert!(erc20.is_operator(accounts.alice, accounts.bob));
+            assert!(!erc20.is_operator(accounts.bob, accounts.alice));
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.charlie, 6_000)
+                .is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.charlie, 4_001),
+                Err(Error::BalanceTooLow)
+            );
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.charlie, 4_000)
+                .is_ok());
+            assert_eq!(erc20.balance_of(accounts.charlie), 10_000);
+
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert!(erc20.set_operator(accounts.bob, true).is_ok());
+            assert!(erc20.set_operator(accounts.bob, false).is_ok());
+            assert!(!erc20.is_operator(accounts.charlie, accounts.bob));
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.charlie, accounts.alice, 1),
+                Err(Error::AllowanceTooLow)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_with_max_fee_rejects_fee_above_cap() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_fee(100, accounts.eve).is_ok());
+            assert!(erc20.transfer_with_max_fee(accounts.bob, 1_000, 10).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 990);
+
+            assert!(erc20.set_fee(200, accounts.eve).is_ok());
+            assert_eq!(
+                erc20.transfer_with_max_fee(accounts.bob, 1_000, 10),
+                Err(Error::FeeTooHigh)
+            );
+            assert_eq!(erc20.balance_of(accounts.alice), 9_000);
+            assert_eq!(erc20.balance_of(accounts.eve), 10);
+        }
+
+        #[ink::test]
+        fn burn_stops_at_min_supply() {
+            let mut erc20 = Erc20::new(10_000);
+            assert_eq!(erc20.min_supply(), 0);
+            assert!(erc20.set_supply_bounds(Some(9_000), None).is_ok());
+            assert_eq!(erc20.min_supply(), 9_000);
+            assert!(erc20.burn(1_000).is_ok());
+            assert_eq!(erc20.total_supply(), 9_000);
+            assert_eq!(erc20.burn(1), Err(Error::BelowMinSupply));
+            assert_eq!(erc20.total_supply(), 9_000);
+        }
+
+        #[ink::test]
+        fn transfer_from_emits_delegated_transfer() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve(accounts.bob, 500).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.charlie, 300)
+                .is_ok());
+
+            // Constructor `Transfer`, `Approve`, the moved `Transfer`, then the
+            // remaining `Approve`, `AllowanceSpent` and `DelegatedTransfer`.
+            let emitted_events = test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(emitted_events.len(), 6);
+            let decoded = <Event as scale::Decode>::decode(&mut &emitted_events[2].data[..])
+                .expect("decoded error");
+            match decoded {
+                Event::Transfer(Transfer {
+                    from, to, value, ..
+                }) => {
+                    assert_eq!(from, Some(accounts.alice));
+                    assert_eq!(to, accounts.charlie);
+                    assert_eq!(value, 300);
+                }
+                _ => panic!("Event do not match"),
+            }
+            let decoded = <Event as scale::Decode>::decode(&mut &emitted_events[5].data[..])
+                .expect("decoded error");
+            match decoded {
+                Event::DelegatedTransfer(DelegatedTransfer {
+                    spender,
+                    from,
+                    to,
+                    value,
+                }) => {
+                    assert_eq!(spender, accounts.bob);
+                    assert_eq!(from, accounts.alice);
+                    assert_eq!(to, accounts.charlie);
+                    assert_eq!(value, 300);
+                }
+                _ => panic!("Event do not match"),
+            }
+        }
+
+        #[ink::test]
+        fn swap_balances_requires_authority_over_both() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 3_000).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                erc20.swap_balances(accounts.alice, accounts.bob),
+                Err(Error::NotOperator)
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.set_operator(accounts.charlie, true).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                erc20.swap_balances(accounts.alice, accounts.bob),
+                Err(Error::NotOperator)
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.set_operator(accounts.charlie, true).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert!(erc20.swap_balances(accounts.alice, accounts.bob).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), 3_000);
+            assert_eq!(erc20.balance_of(accounts.bob), 7_000);
+            assert_eq!(erc20.total_supply(), 10_000);
+        }
+
+        #[ink::test]
+        fn transfer_topics_carry_tag() {
+            let tag = *b"TOKEN-01";
+            let mut erc20 = Erc20::new_with_tag(10_000, tag);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(erc20.tag(), tag);
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
+
+            let emitted_events = test::recorded_events().collect::<Vec<_>>();
+            let event = &emitted_events[1];
+            // Short topics are stored verbatim, prefixed with the field path.
+            let mut expected = [0u8; 32];
+            let prefixed = [&b"Erc20::Transfer::tag"[..], &tag[..]].concat();
+            expected[..prefixed.len()].copy_from_slice(&prefixed);
+            assert_eq!(event.topics.len(), 4);
+            assert_eq!(event.topics[3], expected.to_vec());
+
+            let decoded =
+                <Event as scale::Decode>::decode(&mut &event.data[..]).expect("decoded error");
+            match decoded {
+                Event::Transfer(Transfer { tag: emitted, .. }) => assert_eq!(emitted, tag),
+                _ => panic!("Event do not match"),
+            }
+        }
+
+        #[ink::test]
+        fn batches_are_limited_to_max_batch() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve(accounts.bob, 1_000).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let transfers = vec![(accounts.alice, accounts.charlie, 1); MAX_BATCH + 1];
+            assert_eq!(
+                erc20.transfer_from_batch(transfers),
+                Err(Error::BatchTooLarge)
+            );
+            assert_eq!(erc20.balance_of(accounts.charlie), 0);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 1_000);
+
+            let transfers = vec![(accounts.alice, accounts.charlie, 1); MAX_BATCH];
+            assert!(erc20.transfer_from_batch(transfers).is_ok());
+            assert_eq!(erc20.balance_of(accounts.charlie), MAX_BATCH as Balance);
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                erc20.revoke_allowances(vec![accounts.bob; MAX_BATCH + 1]),
+                Err(Error::BatchTooLarge)
+            );
+            assert_eq!(
+                erc20.allowance(accounts.alice, accounts.bob),
+                1_000 - MAX_BATCH as Balance
+            );
+            assert!(erc20
+                .revoke_allowances(vec![accounts.bob; MAX_BATCH])
+                .is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+
+            let too_many = vec![accounts.bob; MAX_BATCH + 1];
+            assert_eq!(
+                erc20.create_pool(too_many.clone(), 300),
+                Err(Error::BatchTooLarge)
+            );
+            assert_eq!(
+                erc20.set_guardians(too_many.clone(), 2),
+                Err(Error::BatchTooLarge)
+            );
+            assert_eq!(
+                erc20.checkpoint_supply(too_many.clone()),
+                Err(Error::BatchTooLarge)
+            );
+            assert_eq!(erc20.balances_labeled(too_many), Err(Error::BatchTooLarge));
+        }
+
+        #[ink::test]
+        fn total_supply_in_units_truncates() {
+            let erc20 = Erc20::new(10_050);
+            assert_eq!(erc20.total_supply_in_units(), 10_050);
+            let erc20 = Erc20::new_with_unit_scale(10_050, 100);
+            assert_eq!(erc20.total_supply_in_units(), 100);
+            assert_eq!(erc20.total_supply(), 10_050);
+        }
+
         #[ink::test]
-        fn constructor_works() {
-            let total_supply = 10_000;
-            let erc20 = Erc20::new(total_supply);
+        fn allowance_spent_skips_infinite_allowances() {
+            let mut erc20 = Erc20::new(10_000);
             let accounts = test::default_accounts::<DefaultEnvironment>();
-            assert_eq!(erc20.total_supply, total_supply);
-            assert_eq!(erc20.balance_of(accounts.alice), total_supply);
+            assert!(erc20.approve(accounts.bob, 500).is_ok());
+            assert!(erc20.approve(accounts.charlie, Balance::MAX).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.django, 200)
+                .is_ok());
 
-            let emitted_events = test::recorded_events().collect::<Vec<_>>();
-            let event = &emitted_events[0];
-            let decoded =
-                <Event as scale::Decode>::decode(&mut &event.data[..]).expect("decoded error");
-            match decoded {
-                Event::Transfer(Transfer { from, to, value }) => {
-                    assert!(from.is_none());
-                    assert_eq!(to, accounts.alice);
-                    assert_eq!(value, total_supply);
-                }
-                _ => panic!("Event do not match"),
-            }
+            let spent = || {
+                test::recorded_events()
+                    .filter_map(|event| {
+                        match <Event as scale::Decode>::decode(&mut &event.data[..]) {
+                            Ok(Event::AllowanceSpent(spent)) => Some(spent),
+                            _ => None,
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let events = spent();
+            assert_eq!(events.len(), 1);
+            assert_eq!(events[0].owner, accounts.alice);
+            assert_eq!(events[0].spender, accounts.bob);
+            assert_eq!(events[0].amount, 200);
+            assert_eq!(events[0].remaining, 300);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 300);
+
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.django, 200)
+                .is_ok());
+            assert_eq!(spent().len(), 1);
         }
 
         #[ink::test]
-        fn transfer_should_work() {
-            let total_supply = 10_000;
-            let transfer_amount = 1_000;
-            let mut erc20 = Erc20::new(total_supply);
+        fn would_trip_breaker_compares_against_threshold() {
+            let mut erc20 = Erc20::new(10_000);
+            assert!(!erc20.would_trip_breaker(Balance::MAX));
+            assert!(erc20.set_breaker_threshold(Some(1_000)).is_ok());
+            assert!(!erc20.would_trip_breaker(1_000));
+            assert!(erc20.would_trip_breaker(1_001));
+            assert!(!erc20.paused());
+        }
+
+        #[ink::test]
+        fn jurisdiction_rules_gate_transfers() {
+            let mut erc20 = Erc20::new(10_000);
             let accounts = test::default_accounts::<DefaultEnvironment>();
-            let res = erc20.transfer(accounts.bob, transfer_amount);
+            assert!(erc20.set_jurisdiction(accounts.alice, Some(1)).is_ok());
+            assert!(erc20.set_jurisdiction(accounts.bob, Some(2)).is_ok());
+            assert!(erc20.set_jurisdiction(accounts.charlie, Some(3)).is_ok());
+            assert!(erc20.set_jurisdiction_forbidden(1, 3, true).is_ok());
+            assert_eq!(erc20.jurisdiction_of(accounts.bob), Some(2));
 
-            assert!(res.is_ok());
+            assert!(erc20.transfer(accounts.bob, 100).is_ok());
             assert_eq!(
-                erc20.balance_of(accounts.alice),
-                total_supply - transfer_amount
+                erc20.transfer(accounts.charlie, 100),
+                Err(Error::JurisdictionForbidden)
+            );
+            assert_eq!(
+                erc20.detect_transfer_restriction(accounts.alice, accounts.charlie, 100),
+                Error::JurisdictionForbidden as u8 + 1
+            );
+
+            // Accounts without a jurisdiction are unrestricted by default.
+            assert!(erc20.transfer(accounts.django, 100).is_ok());
+            assert!(erc20.set_restrict_unset_jurisdictions(true).is_ok());
+            assert_eq!(
+                erc20.transfer(accounts.django, 100),
+                Err(Error::JurisdictionForbidden)
+            );
+            assert!(erc20.set_jurisdiction(accounts.bob, None).is_ok());
+            assert_eq!(
+                erc20.transfer(accounts.bob, 100),
+                Err(Error::JurisdictionForbidden)
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.set_jurisdiction(accounts.bob, Some(1)),
+                Err(Error::NotOwner)
             );
-            assert_eq!(erc20.balance_of(accounts.bob), transfer_amount);
         }
 
         #[ink::test]
-        fn invalid_transfer_should_fail() {
-            let total_supply = 10_000;
-            let transfer_amount = 1_000;
-            let mut erc20 = Erc20::new(total_supply);
+        fn allowance_ratio_bps_clamps_to_balance() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(erc20.allowance_ratio_bps(accounts.alice, accounts.bob), 0);
+            assert!(erc20.approve(accounts.bob, 2_500).is_ok());
+            assert_eq!(
+                erc20.allowance_ratio_bps(accounts.alice, accounts.bob),
+                2_500
+            );
+            assert!(erc20.approve(accounts.bob, 20_000).is_ok());
+            assert_eq!(
+                erc20.allowance_ratio_bps(accounts.alice, accounts.bob),
+                MAX_BPS
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert!(erc20.approve(accounts.bob, 100).is_ok());
+            assert_eq!(erc20.allowance_ratio_bps(accounts.charlie, accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn mint_proportional_conserves_total() {
+            let mut erc20 = Erc20::new(10_000);
             let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 3_000).is_ok());
+            assert!(erc20.transfer(accounts.charlie, 1_000).is_ok());
+            let holders = vec![accounts.alice, accounts.bob, accounts.charlie, accounts.bob];
+
+            // 1_000 splits 600/300/100 exactly.
+            assert!(erc20.mint_proportional(1_000, holders.clone()).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), 6_600);
+            assert_eq!(erc20.balance_of(accounts.bob), 3_300);
+            assert_eq!(erc20.balance_of(accounts.charlie), 1_100);
+            assert_eq!(erc20.total_supply(), 11_000);
+
+            // 7 splits 4.2/2.1/0.7; the leftover token goes to alice.
+            assert!(erc20.mint_proportional(7, holders).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), 6_605);
+            assert_eq!(erc20.balance_of(accounts.bob), 3_302);
+            assert_eq!(erc20.balance_of(accounts.charlie), 1_100);
+            assert_eq!(erc20.total_supply(), 11_007);
+
+            assert_eq!(
+                erc20.mint_proportional(7, vec![accounts.django]),
+                Err(Error::BalanceTooLow)
+            );
             test::set_caller::<DefaultEnvironment>(accounts.bob);
-            let res = erc20.transfer(accounts.bob, transfer_amount);
-            assert_eq!(res, Err(Error::BalanceTooLow));
+            assert_eq!(
+                erc20.mint_proportional(7, vec![accounts.bob]),
+                Err(Error::NotOwner)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_from_by_fails_after_deadline() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.approve(accounts.bob, 500).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            test::set_block_timestamp::<DefaultEnvironment>(100);
+            assert!(erc20
+                .transfer_from_by(accounts.alice, accounts.charlie, 200, 100)
+                .is_ok());
+            assert_eq!(erc20.balance_of(accounts.charlie), 200);
+
+            test::set_block_timestamp::<DefaultEnvironment>(101);
+            assert_eq!(
+                erc20.transfer_from_by(accounts.alice, accounts.charlie, 200, 100),
+                Err(Error::Expired)
+            );
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 300);
+            assert_eq!(erc20.balance_of(accounts.charlie), 200);
+        }
+
+        #[ink::test]
+        fn dust_transfers_are_dropped_when_enabled() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 5).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 5);
+            assert_eq!(test::recorded_events().count(), 2);
+
+            assert!(erc20.set_drop_dust(true, 10).is_ok());
+            assert!(erc20.approve(accounts.charlie, 100).is_ok());
+            let events_before = test::recorded_events().count();
+            assert!(erc20.transfer(accounts.bob, 9).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert!(erc20.transfer_from(accounts.alice, accounts.bob, 9).is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.charlie), 100);
+            assert_eq!(erc20.balance_of(accounts.bob), 5);
+            assert_eq!(test::recorded_events().count(), events_before);
+            test::set_caller::<DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 9),
+                Err(Error::AllowanceTooLow)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            let receipt = erc20.transfer_receipt(accounts.bob, 9).unwrap();
+            assert_eq!((receipt.gross, receipt.net, receipt.fee), (0, 0, 0));
+            assert_eq!(
+                erc20.simulate_transfer(accounts.alice, accounts.bob, 9),
+                (9_995, 5)
+            );
+            assert_eq!(
+                erc20.transfer_split(accounts.bob, accounts.eve, 100, 9_500),
+                Err(Error::DustLeg)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+
+            assert!(erc20
+                .transfer_from(accounts.alice, accounts.bob, 10)
+                .is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 15);
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.set_drop_dust(false, 10).is_ok());
+            assert!(erc20.transfer(accounts.bob, 9).is_ok());
+            assert_eq!(erc20.balance_of(accounts.bob), 24);
+        }
+
+        #[ink::test]
+        fn swap_balances_applies_transfer_checks() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.transfer(accounts.bob, 3_000).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.set_operator(accounts.alice, true).is_ok());
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+
+            assert!(erc20.freeze_amount(accounts.bob, 1).is_ok());
+            assert_eq!(
+                erc20.swap_balances(accounts.alice, accounts.bob),
+                Err(Error::BalanceFrozen)
+            );
+            assert!(erc20.unfreeze_amount(accounts.bob, 1).is_ok());
+
+            assert!(erc20.pause().is_ok());
+            assert_eq!(
+                erc20.swap_balances(accounts.alice, accounts.bob),
+                Err(Error::Paused)
+            );
+            assert!(erc20.unpause().is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), 7_000);
+            assert_eq!(erc20.balance_of(accounts.bob), 3_000);
+
+            assert!(erc20.swap_balances(accounts.alice, accounts.bob).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), 3_000);
+            assert_eq!(erc20.balance_of(accounts.bob), 7_000);
+        }
+
+        #[ink::test]
+        fn timelock_covers_supply_and_fee_changes() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.set_min_delay(100).is_ok());
+            assert_eq!(
+                erc20.authorize_mint(accounts.bob, 500),
+                Err(Error::TimelockNotElapsed)
+            );
+            assert_eq!(erc20.rebase(1_000), Err(Error::TimelockNotElapsed));
+            assert_eq!(
+                erc20.set_round_up_fees(true),
+                Err(Error::TimelockNotElapsed)
+            );
+
+            let actions = [
+                AdminAction::AuthorizeMint(accounts.bob, 500),
+                AdminAction::Rebase(1_000),
+                AdminAction::SetRoundUpFees(true),
+            ];
+            for action in actions {
+                assert!(erc20.queue_action(action).is_ok());
+            }
+            test::set_block_timestamp::<DefaultEnvironment>(100);
+            for id in 0..3 {
+                assert!(erc20.execute_action(id).is_ok());
+            }
+            assert_eq!(erc20.claimable_mint(accounts.bob), 500);
+            assert_eq!(erc20.total_supply(), 11_000);
+            assert!(erc20.config().round_up_fees);
+        }
+
+        #[ink::test]
+        fn revocations_clear_period_allowances() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let day = 86_400_000;
+            let grant = |erc20: &mut Erc20| {
+                test::set_caller::<DefaultEnvironment>(accounts.alice);
+                assert!(erc20.approve_per_period(accounts.bob, 10, day).is_ok());
+                assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 10);
+            };
+            let revoked = |erc20: &mut Erc20| {
+                assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+                assert_eq!(erc20.period_allowance(accounts.alice, accounts.bob), None);
+                test::set_caller::<DefaultEnvironment>(accounts.bob);
+                assert_eq!(
+                    erc20.transfer_from(accounts.alice, accounts.bob, 1),
+                    Err(Error::AllowanceTooLow)
+                );
+            };
+
+            grant(&mut erc20);
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(erc20.transfer_from(accounts.alice, accounts.bob, 4).is_ok());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 6);
+            test::set_block_timestamp::<DefaultEnvironment>(day);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 10);
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert!(erc20.approve(accounts.bob, 0).is_ok());
+            revoked(&mut erc20);
+
+            grant(&mut erc20);
+            assert!(erc20.decrease_allowance_to_zero(accounts.bob).is_ok());
+            revoked(&mut erc20);
+
+            grant(&mut erc20);
+            assert!(erc20.revoke_allowances(vec![accounts.bob]).is_ok());
+            revoked(&mut erc20);
+
+            grant(&mut erc20);
+            assert!(erc20.revoke_all_allowances().is_ok());
+            revoked(&mut erc20);
+        }
+
+        #[ink::test]
+        fn burn_cannot_touch_frozen_tokens() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.freeze_amount(accounts.alice, 4_000).is_ok());
+            assert_eq!(erc20.burn(6_001), Err(Error::BalanceFrozen));
+            assert!(erc20.burn(6_000).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), 4_000);
+            assert_eq!(erc20.burn(1), Err(Error::BalanceFrozen));
+        }
+
+        #[ink::test]
+        fn burn_cannot_touch_staked_tokens() {
+            let mut erc20 = Erc20::new(10_000);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert!(erc20.stake(4_000).is_ok());
+            assert_eq!(erc20.burn(6_001), Err(Error::BalanceStaked));
+            assert!(erc20.burn(6_000).is_ok());
+            assert_eq!(erc20.balance_of(accounts.alice), 4_000);
+            assert_eq!(erc20.staked_of(accounts.alice), 4_000);
+            assert!(erc20.unstake(4_000).is_ok());
+            assert!(erc20.burn(4_000).is_ok());
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+
+        /// A helper function used for calling contract messages.
+        use ink_e2e::build_message;
+        use mock_approvee::MockApproveeRef;
+        use mock_consumer::MockConsumerRef;
+        use mock_oracle::MockOracleRef;
+        use mock_router::MockRouterRef;
+        use mock_spender::MockSpenderRef;
+        use mock_validator::MockValidatorRef;
+
+        /// The End-to-End test `Result` type.
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        /// construct the contract, and transfer from alice to bob, at last check the balance of alice.
+        #[ink_e2e::test]
+        async fn e2e_transfer_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+            let total_supply = 100_000;
+            let transfer_amount = 1_000;
+            let constructor = Erc20Ref::new(total_supply);
+
+            let contract_account_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+
+            let alice_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let bob_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+            let transfer_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.transfer(bob_acc, transfer_amount));
+
+            let res = client.call(&ink_e2e::alice(), transfer_msg, 0, None).await;
+            assert!(res.is_ok());
+
+            let balance_of_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.balance_of(alice_acc));
+
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
+                .await;
+            assert_eq!(res.return_value(), total_supply - transfer_amount);
+
+            Ok(())
+        }
+
+        /// test for approve first, then call transfer_from method. at last check the allowance,and balance.
+        #[ink_e2e::test]
+        async fn e2e_approve_then_transfer_from_works(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            let total_supply = 100_000;
+            let transfer_amount = 1_000;
+            let constructor = Erc20Ref::new(total_supply);
+
+            let contract_account_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+
+            let alice_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let bob_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+            let charlie_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Charlie);
+
+            let approve_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.approve(bob_acc, transfer_amount));
+            let res = client.call(&ink_e2e::alice(), approve_msg, 0, None).await;
+            assert!(res.is_ok());
+
+            let transfer_from_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.transfer_from(alice_acc, charlie_acc, transfer_amount));
+            let res = client
+                .call(&ink_e2e::bob(), transfer_from_msg, 0, None)
+                .await;
+            assert!(res.is_ok());
+
+            let balance_of_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.balance_of(alice_acc));
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
+                .await;
+            assert_eq!(res.return_value(), total_supply - transfer_amount);
+
+            let allowance_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.allowance(alice_acc, bob_acc));
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &allowance_msg, 0, None)
+                .await;
+            assert_eq!(res.return_value(), 0);
+
+            Ok(())
+        }
+
+        /// register a validator that rejects everything, check the transfer is blocked,
+        /// then unset it and check the transfer goes through.
+        #[ink_e2e::test(additional_contracts = "mocks/validator/Cargo.toml")]
+        async fn e2e_validator_blocks_transfer(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+            let total_supply = 100_000;
+            let transfer_amount = 1_000;
+            let constructor = Erc20Ref::new(total_supply);
+            let contract_account_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let validator_account_id = client
+                .instantiate(
+                    "mock_validator",
+                    &ink_e2e::alice(),
+                    MockValidatorRef::new(false),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+
+            let bob_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+            let set_validator_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.set_validator(Some(validator_account_id.clone())));
+            let res = client
+                .call(&ink_e2e::alice(), set_validator_msg, 0, None)
+                .await;
+            assert!(res.is_ok());
+
+            let transfer_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.transfer(bob_acc, transfer_amount));
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &transfer_msg, 0, None)
+                .await;
+            assert_eq!(res.return_value(), Err(Error::ValidationFailed));
+
+            let unset_validator_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.set_validator(None));
+            let res = client
+                .call(&ink_e2e::alice(), unset_validator_msg, 0, None)
+                .await;
+            assert!(res.is_ok());
+
+            let res = client.call(&ink_e2e::alice(), transfer_msg, 0, None).await;
+            assert!(res.is_ok());
+
+            let balance_of_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.balance_of(bob_acc));
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
+                .await;
+            assert_eq!(res.return_value(), transfer_amount);
+
+            Ok(())
         }
 
-        #[ink::test]
-        fn approve_then_transfer_should_work() {
-            let total_supply = 10_000;
+        /// approve a spender contract whose callback pulls the allowance in the same transaction.
+        #[ink_e2e::test(additional_contracts = "mocks/spender/Cargo.toml")]
+        async fn e2e_approve_and_call_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+            let total_supply = 100_000;
             let approve_amount = 1_000;
-            let transfer_amount = 1_000;
-            let mut erc20 = Erc20::new(total_supply);
-            let accounts = test::default_accounts::<DefaultEnvironment>();
-            let res = erc20.approve(accounts.bob, approve_amount);
-            assert!(res.is_ok());
-            test::set_caller::<DefaultEnvironment>(accounts.bob);
-            let res = erc20.transfer_from(accounts.alice, accounts.charlie, transfer_amount);
-            assert!(res.is_ok());
-            let allowance = erc20.allowance(accounts.alice, accounts.bob);
-            assert_eq!(allowance, approve_amount - transfer_amount);
-        }
+            let constructor = Erc20Ref::new(total_supply);
+            let contract_account_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let spender_account_id = client
+                .instantiate(
+                    "mock_spender",
+                    &ink_e2e::alice(),
+                    MockSpenderRef::new(),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
 
-        #[ink::test]
-        fn transfer_from_failed_when_allowance_too_low() {
-            let total_supply = 10_000;
-            let approve_amount = 999;
-            let transfer_amount = 1_000;
-            let mut erc20 = Erc20::new(total_supply);
-            let accounts = test::default_accounts::<DefaultEnvironment>();
-            let res = erc20.approve(accounts.bob, approve_amount);
+            let alice_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+
+            let approve_and_call_msg =
+                build_message::<Erc20Ref>(contract_account_id.clone()).call(|erc20| {
+                    erc20.approve_and_call(spender_account_id.clone(), approve_amount, Vec::new())
+                });
+            let res = client
+                .call(&ink_e2e::alice(), approve_and_call_msg, 0, None)
+                .await;
             assert!(res.is_ok());
-            test::set_caller::<DefaultEnvironment>(accounts.bob);
-            let res = erc20.transfer_from(accounts.alice, accounts.charlie, transfer_amount);
-            assert_eq!(res, Err(Error::AllowanceTooLow));
-        }
-    }
 
-    #[cfg(all(test, feature = "e2e-tests"))]
-    mod e2e_tests {
-        use super::*;
+            let balance_of_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.balance_of(spender_account_id.clone()));
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
+                .await;
+            assert_eq!(res.return_value(), approve_amount);
 
-        /// A helper function used for calling contract messages.
-        use ink_e2e::build_message;
+            let allowance_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.allowance(alice_acc, spender_account_id.clone()));
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &allowance_msg, 0, None)
+                .await;
+            assert_eq!(res.return_value(), 0);
 
-        /// The End-to-End test `Result` type.
-        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+            let callbacks_msg = build_message::<MockSpenderRef>(spender_account_id.clone())
+                .call(|spender| spender.callbacks());
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &callbacks_msg, 0, None)
+                .await;
+            assert_eq!(res.return_value(), 1);
 
-        /// construct the contract, and transfer from alice to bob, at last check the balance of alice.
+            Ok(())
+        }
+
+        /// dry-run remaining_gas and check it reports a non-zero figure.
         #[ink_e2e::test]
-        async fn e2e_transfer_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+        async fn e2e_remaining_gas_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+            let constructor = Erc20Ref::new(100_000);
+            let contract_account_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+
+            let remaining_gas_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.remaining_gas());
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &remaining_gas_msg, 0, None)
+                .await;
+            assert!(res.return_value() > 0);
+
+            Ok(())
+        }
+
+        /// register an oracle reporting a price under the floor, check transfers are
+        /// blocked, then raise the price and check they go through.
+        #[ink_e2e::test(additional_contracts = "mocks/oracle/Cargo.toml")]
+        async fn e2e_oracle_price_floor_blocks_transfer(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
             let total_supply = 100_000;
             let transfer_amount = 1_000;
             let constructor = Erc20Ref::new(total_supply);
-
             let contract_account_id = client
                 .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
                 .await
                 .expect("instantiate failed")
                 .account_id;
+            let oracle_account_id = client
+                .instantiate(
+                    "mock_oracle",
+                    &ink_e2e::alice(),
+                    MockOracleRef::new(95),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
 
-            let alice_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
             let bob_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
 
+            let set_oracle_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.set_oracle(Some(oracle_account_id.clone()), 100));
+            let res = client
+                .call(&ink_e2e::alice(), set_oracle_msg, 0, None)
+                .await;
+            assert!(res.is_ok());
+
             let transfer_msg = build_message::<Erc20Ref>(contract_account_id.clone())
                 .call(|erc20| erc20.transfer(bob_acc, transfer_amount));
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &transfer_msg, 0, None)
+                .await;
+            assert_eq!(res.return_value(), Err(Error::PriceFloorBreached));
+
+            let set_price_msg = build_message::<MockOracleRef>(oracle_account_id.clone())
+                .call(|oracle| oracle.set_price(100));
+            let res = client.call(&ink_e2e::alice(), set_price_msg, 0, None).await;
+            assert!(res.is_ok());
 
             let res = client.call(&ink_e2e::alice(), transfer_msg, 0, None).await;
             assert!(res.is_ok());
 
             let balance_of_msg = build_message::<Erc20Ref>(contract_account_id.clone())
-                .call(|erc20| erc20.balance_of(alice_acc));
-
+                .call(|erc20| erc20.balance_of(bob_acc));
             let res = client
                 .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
                 .await;
-            assert_eq!(res.return_value(), total_supply - transfer_amount);
+            assert_eq!(res.return_value(), transfer_amount);
 
             Ok(())
         }
 
-        /// test for approve first, then call transfer_from method. at last check the allowance,and balance.
         #[ink_e2e::test]
-        async fn e2e_approve_then_transfer_from_works(
+        async fn e2e_transfer_with_tip_delivers_tokens_and_tip(
             mut client: ink_e2e::Client<C, E>,
         ) -> E2EResult<()> {
-            let total_supply = 100_000;
-            let transfer_amount = 1_000;
-            let constructor = Erc20Ref::new(total_supply);
+            let constructor = Erc20Ref::new(100_000);
+            let contract_account_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let bob_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+            let tip = 1_000_000;
+            let native_before = client.balance(bob_acc.clone()).await?;
+
+            let tip_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.transfer_with_tip(bob_acc, 500));
+            client
+                .call(&ink_e2e::alice(), tip_msg, tip, None)
+                .await
+                .expect("transfer_with_tip failed");
+
+            let balance_of_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.balance_of(bob_acc));
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
+                .await;
+            assert_eq!(res.return_value(), 500);
+            assert_eq!(client.balance(bob_acc).await?, native_before + tip);
 
+            Ok(())
+        }
+
+        /// Route a transfer through the mock router, then check that a worse
+        /// rate fails the `min_out` slippage check.
+        #[ink_e2e::test(additional_contracts = "mocks/router/Cargo.toml")]
+        async fn e2e_transfer_via_router_checks_slippage(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            let constructor = Erc20Ref::new(100_000);
             let contract_account_id = client
                 .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
                 .await
                 .expect("instantiate failed")
                 .account_id;
+            let router_account_id = client
+                .instantiate(
+                    "mock_router",
+                    &ink_e2e::alice(),
+                    MockRouterRef::new(10_000),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let bob_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
 
-            let alice_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let set_router_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.set_router(Some(router_account_id.clone())));
+            let res = client
+                .call(&ink_e2e::alice(), set_router_msg, 0, None)
+                .await;
+            assert!(res.is_ok());
+
+            let route_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.transfer_via_router(bob_acc, 1_000, 900));
+            let res = client
+                .call(&ink_e2e::alice(), route_msg, 0, None)
+                .await
+                .expect("transfer_via_router failed");
+            assert_eq!(res.return_value(), Ok(1_000));
+
+            let balance_of_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.balance_of(router_account_id.clone()));
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
+                .await;
+            assert_eq!(res.return_value(), 0);
+
+            let balance_of_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.balance_of(bob_acc));
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
+                .await;
+            assert_eq!(res.return_value(), 1_000);
+
+            let set_rate_msg = build_message::<MockRouterRef>(router_account_id.clone())
+                .call(|router| router.set_rate(5_000));
+            let res = client.call(&ink_e2e::alice(), set_rate_msg, 0, None).await;
+            assert!(res.is_ok());
+
+            let route_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.transfer_via_router(bob_acc, 1_000, 900));
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &route_msg, 0, None)
+                .await;
+            assert_eq!(res.return_value(), Err(Error::RouterCallFailed));
+
+            Ok(())
+        }
+
+        /// With spender verification on, a contract spender that rejects the
+        /// approval cannot be approved, while accepting contracts and plain
+        /// accounts can.
+        #[ink_e2e::test(additional_contracts = "mocks/approvee/Cargo.toml")]
+        async fn e2e_verify_spenders_rejects_unwilling_contract(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            let constructor = Erc20Ref::new(100_000);
+            let contract_account_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let accepting = client
+                .instantiate(
+                    "mock_approvee",
+                    &ink_e2e::alice(),
+                    MockApproveeRef::new(true),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let rejecting = client
+                .instantiate(
+                    "mock_approvee",
+                    &ink_e2e::alice(),
+                    MockApproveeRef::new(false),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
             let bob_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
-            let charlie_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Charlie);
+
+            let verify_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.set_verify_spenders(true));
+            let res = client.call(&ink_e2e::alice(), verify_msg, 0, None).await;
+            assert!(res.is_ok());
 
             let approve_msg = build_message::<Erc20Ref>(contract_account_id.clone())
-                .call(|erc20| erc20.approve(bob_acc, transfer_amount));
+                .call(|erc20| erc20.approve(accepting.clone(), 100));
             let res = client.call(&ink_e2e::alice(), approve_msg, 0, None).await;
             assert!(res.is_ok());
 
-            let transfer_from_msg = build_message::<Erc20Ref>(contract_account_id.clone())
-                .call(|erc20| erc20.transfer_from(alice_acc, charlie_acc, transfer_amount));
+            let approve_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.approve(rejecting.clone(), 100));
             let res = client
-                .call(&ink_e2e::bob(), transfer_from_msg, 0, None)
+                .call_dry_run(&ink_e2e::alice(), &approve_msg, 0, None)
                 .await;
+            assert_eq!(res.return_value(), Err(Error::SpenderRejected));
+
+            let approve_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.approve(bob_acc, 100));
+            let res = client.call(&ink_e2e::alice(), approve_msg, 0, None).await;
             assert!(res.is_ok());
 
-            let balance_of_msg = build_message::<Erc20Ref>(contract_account_id.clone())
-                .call(|erc20| erc20.balance_of(alice_acc));
+            Ok(())
+        }
+
+        /// Enable seeding, then check that only the first transfer to an
+        /// account also sends it native balance, even if it empties itself
+        /// and receives tokens again.
+        #[ink_e2e::test]
+        async fn e2e_first_transfer_seeds_new_holder(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            let constructor = Erc20Ref::new(100_000);
+            let contract_account_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let ferdie_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Ferdie);
+            let seed = 1_000_000;
+
+            let seed_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.set_seed_new_holders(true, seed));
+            client
+                .call(&ink_e2e::alice(), seed_msg, 10 * seed, None)
+                .await
+                .expect("set_seed_new_holders failed");
+
+            let native_before = client.balance(ferdie_acc.clone()).await?;
+            let transfer_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.transfer(ferdie_acc, 500));
+            client
+                .call(&ink_e2e::alice(), transfer_msg, 0, None)
+                .await
+                .expect("first transfer failed");
+            assert_eq!(
+                client.balance(ferdie_acc.clone()).await?,
+                native_before + seed
+            );
+
+            let transfer_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.transfer(ferdie_acc, 500));
+            client
+                .call(&ink_e2e::alice(), transfer_msg, 0, None)
+                .await
+                .expect("second transfer failed");
+            assert_eq!(
+                client.balance(ferdie_acc.clone()).await?,
+                native_before + seed
+            );
+
+            // Ferdie sends everything back and receives it again.
+            let alice_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let return_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.transfer(alice_acc, 1_000));
+            client
+                .call(&ink_e2e::ferdie(), return_msg, 0, None)
+                .await
+                .expect("return transfer failed");
+            let native_before = client.balance(ferdie_acc.clone()).await?;
+            let transfer_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.transfer(ferdie_acc, 500));
+            client
+                .call(&ink_e2e::alice(), transfer_msg, 0, None)
+                .await
+                .expect("third transfer failed");
+            assert_eq!(client.balance(ferdie_acc).await?, native_before);
+
+            Ok(())
+        }
+
+        /// A second contract reads balances and allowances through the
+        /// token's regular messages.
+        #[ink_e2e::test(additional_contracts = "mocks/consumer/Cargo.toml")]
+        async fn e2e_consumer_contract_reads_balance_and_allowance(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            let constructor = Erc20Ref::new(100_000);
+            let contract_account_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let consumer_account_id = client
+                .instantiate(
+                    "mock_consumer",
+                    &ink_e2e::alice(),
+                    MockConsumerRef::new(contract_account_id.clone()),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let alice_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let bob_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+            let approve_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.approve(bob_acc, 300));
+            client
+                .call(&ink_e2e::alice(), approve_msg, 0, None)
+                .await
+                .expect("approve failed");
+
+            let balance_msg = build_message::<MockConsumerRef>(consumer_account_id.clone())
+                .call(|consumer| consumer.balance_of(alice_acc));
             let res = client
-                .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
+                .call_dry_run(&ink_e2e::alice(), &balance_msg, 0, None)
                 .await;
-            assert_eq!(res.return_value(), total_supply - transfer_amount);
+            assert_eq!(res.return_value(), 100_000);
 
-            let allowance_msg = build_message::<Erc20Ref>(contract_account_id.clone())
-                .call(|erc20| erc20.allowance(alice_acc, bob_acc));
+            let allowance_msg = build_message::<MockConsumerRef>(consumer_account_id.clone())
+                .call(|consumer| consumer.allowance(alice_acc, bob_acc));
             let res = client
                 .call_dry_run(&ink_e2e::alice(), &allowance_msg, 0, None)
-                .await; 
+                .await;
+            assert_eq!(res.return_value(), 300);
+
+            Ok(())
+        }
+
+        /// An over-threshold transfer must leave the contract paused on chain,
+        /// where a failing call would have reverted the pause.
+        #[ink_e2e::test]
+        async fn e2e_circuit_breaker_pause_persists(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            let constructor = Erc20Ref::new(100_000);
+            let contract_account_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let bob_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+            let set_threshold_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.set_breaker_threshold(Some(1_000)));
+            let res = client
+                .call(&ink_e2e::alice(), set_threshold_msg, 0, None)
+                .await;
+            assert!(res.is_ok());
+
+            let transfer_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.transfer(bob_acc, 1_001));
+            let res = client
+                .call(&ink_e2e::alice(), transfer_msg, 0, None)
+                .await
+                .expect("transfer failed");
+            assert_eq!(res.return_value(), Ok(()));
+
+            let paused_msg =
+                build_message::<Erc20Ref>(contract_account_id.clone()).call(|erc20| erc20.paused());
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &paused_msg, 0, None)
+                .await;
+            assert!(res.return_value());
+
+            let balance_of_msg = build_message::<Erc20Ref>(contract_account_id.clone())
+                .call(|erc20| erc20.balance_of(bob_acc));
+            let res = client
+                .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
+                .await;
             assert_eq!(res.return_value(), 0);
 
             Ok(())
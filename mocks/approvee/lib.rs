@@ -0,0 +1,26 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::mock_approvee::MockApproveeRef;
+
+/// A contract spender used by the erc20 e2e tests. Accepts or rejects
+/// approvals depending on how it was configured.
+#[ink::contract]
+mod mock_approvee {
+    #[ink(storage)]
+    pub struct MockApprovee {
+        accept: bool,
+    }
+
+    impl MockApprovee {
+        #[ink(constructor)]
+        pub fn new(accept: bool) -> Self {
+            Self { accept }
+        }
+
+        /// Probed by the token before it grants this contract an allowance.
+        #[ink(message)]
+        pub fn accepts_approval(&self, _owner: AccountId, _value: Balance) -> bool {
+            self.accept
+        }
+    }
+}
@@ -0,0 +1,61 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::mock_consumer::MockConsumerRef;
+
+/// A contract reading the erc20 token, used by the e2e tests to show how
+/// other contracts query balances and allowances.
+#[ink::contract]
+mod mock_consumer {
+    use ink::env::{
+        call::{build_call, ExecutionInput, Selector},
+        DefaultEnvironment,
+    };
+
+    #[ink(storage)]
+    pub struct MockConsumer {
+        token: AccountId,
+    }
+
+    impl MockConsumer {
+        #[ink(constructor)]
+        pub fn new(token: AccountId) -> Self {
+            Self { token }
+        }
+
+        /// Reads `owner`'s balance from the token, or zero if the call fails.
+        #[ink(message)]
+        pub fn balance_of(&self, owner: AccountId) -> Balance {
+            build_call::<DefaultEnvironment>()
+                .call(self.token)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("balance_of")))
+                        .push_arg(owner),
+                )
+                .returns::<Balance>()
+                .try_invoke()
+                .ok()
+                .and_then(|res| res.ok())
+                .unwrap_or_default()
+        }
+
+        /// Reads the allowance of `spender` over `owner` from the token, or
+        /// zero if the call fails.
+        #[ink(message)]
+        pub fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            build_call::<DefaultEnvironment>()
+                .call(self.token)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("allowance")))
+                        .push_arg(owner)
+                        .push_arg(spender),
+                )
+                .returns::<Balance>()
+                .try_invoke()
+                .ok()
+                .and_then(|res| res.ok())
+                .unwrap_or_default()
+        }
+    }
+}
@@ -0,0 +1,31 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::mock_oracle::MockOracleRef;
+
+/// A price oracle used by the erc20 e2e tests. Reports whatever price it was
+/// last given.
+#[ink::contract]
+mod mock_oracle {
+    #[ink(storage)]
+    pub struct MockOracle {
+        price: u128,
+    }
+
+    impl MockOracle {
+        #[ink(constructor)]
+        pub fn new(price: u128) -> Self {
+            Self { price }
+        }
+
+        #[ink(message)]
+        pub fn set_price(&mut self, price: u128) {
+            self.price = price;
+        }
+
+        /// Queried by the token before every transfer.
+        #[ink(message)]
+        pub fn price(&self) -> u128 {
+            self.price
+        }
+    }
+}
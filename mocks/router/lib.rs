@@ -0,0 +1,54 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::mock_router::MockRouterRef;
+
+/// A DEX router used by the erc20 e2e tests. Delivers tokens it was sent at a
+/// fixed rate and keeps the rest.
+#[ink::contract]
+mod mock_router {
+    use ink::env::call::{build_call, ExecutionInput, Selector};
+    use ink::env::DefaultEnvironment;
+
+    #[ink(storage)]
+    pub struct MockRouter {
+        rate_bps: u16,
+    }
+
+    impl MockRouter {
+        #[ink(constructor)]
+        pub fn new(rate_bps: u16) -> Self {
+            Self { rate_bps }
+        }
+
+        #[ink(message)]
+        pub fn set_rate(&mut self, rate_bps: u16) {
+            self.rate_bps = rate_bps;
+        }
+
+        /// Called by the token after moving `value` into the router. Sends the
+        /// output amount on to `to` by calling back into the token's
+        /// `transfer`, and returns it.
+        #[ink(message)]
+        pub fn route(&mut self, _from: AccountId, to: AccountId, value: Balance) -> Balance {
+            let out = value / 10_000 * Balance::from(self.rate_bps)
+                + value % 10_000 * Balance::from(self.rate_bps) / 10_000;
+            let token = self.env().caller();
+            // The token's error enum only has unit variants, so it decodes as
+            // its one byte discriminant.
+            let result = build_call::<DefaultEnvironment>()
+                .call(token)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("transfer")))
+                        .push_arg(to)
+                        .push_arg(out),
+                )
+                .returns::<core::result::Result<(), u8>>()
+                .try_invoke();
+            match result {
+                Ok(Ok(Ok(()))) => out,
+                _ => 0,
+            }
+        }
+    }
+}
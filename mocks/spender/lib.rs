@@ -0,0 +1,52 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::mock_spender::MockSpenderRef;
+
+/// A spender used by the erc20 e2e tests. When notified of an approval it
+/// immediately pulls the approved amount from the owner.
+#[ink::contract]
+mod mock_spender {
+    use ink::env::call::{build_call, ExecutionInput, Selector};
+    use ink::env::DefaultEnvironment;
+    use ink::prelude::vec::Vec;
+
+    #[ink(storage)]
+    #[derive(Default)]
+    pub struct MockSpender {
+        callbacks: u32,
+    }
+
+    impl MockSpender {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        #[ink(message)]
+        pub fn callbacks(&self) -> u32 {
+            self.callbacks
+        }
+
+        /// Called by the token from `approve_and_call`. Spends the new
+        /// allowance by calling back into the token's `transfer_from`.
+        #[ink(message)]
+        pub fn on_approval(&mut self, owner: AccountId, value: Balance, _data: Vec<u8>) -> bool {
+            self.callbacks += 1;
+            let token = self.env().caller();
+            // The token's error enum only has unit variants, so it decodes as
+            // its one byte discriminant.
+            let result = build_call::<DefaultEnvironment>()
+                .call(token)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("transfer_from")))
+                        .push_arg(owner)
+                        .push_arg(self.env().account_id())
+                        .push_arg(value),
+                )
+                .returns::<core::result::Result<(), u8>>()
+                .try_invoke();
+            matches!(result, Ok(Ok(Ok(()))))
+        }
+    }
+}
@@ -0,0 +1,31 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::mock_validator::MockValidatorRef;
+
+/// A transfer validator used by the erc20 e2e tests. It approves or rejects
+/// every transfer depending on a single flag.
+#[ink::contract]
+mod mock_validator {
+    #[ink(storage)]
+    pub struct MockValidator {
+        allow: bool,
+    }
+
+    impl MockValidator {
+        #[ink(constructor)]
+        pub fn new(allow: bool) -> Self {
+            Self { allow }
+        }
+
+        #[ink(message)]
+        pub fn set_allow(&mut self, allow: bool) {
+            self.allow = allow;
+        }
+
+        /// Called by the token before every transfer.
+        #[ink(message)]
+        pub fn validate(&self, _from: AccountId, _to: AccountId, _value: Balance) -> bool {
+            self.allow
+        }
+    }
+}